@@ -8,6 +8,7 @@ use core::panic;
 use std::collections::HashSet;
 
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 
 use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Type};
@@ -18,46 +19,1579 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Type};
 type EnumData = (Ident, Vec<(Ident, Type)>);
 
 /// Derive [`From<T>`] implementations for a type enumerating enum.
-#[proc_macro_derive(From)]
+///
+/// Types can be any `'static` types, including boxed trait objects and function
+/// pointers, which are distinguished by their exact type:
+/// ```
+/// use try_as_traits::{TryAsRef, TypeNames, TypedContainer};
+///
+/// type Transform = Box<dyn Fn(i64) -> i64 + Send>;
+///
+/// #[derive(try_as_macros::From, try_as_macros::TryAsRef, try_as_macros::TypedContainer, try_as_macros::TypeNames)]
+/// enum Callback {
+///     Closure(Box<dyn Fn(i64) -> i64 + Send>),
+///     Raw(fn() -> u32),
+/// }
+///
+/// let callback = Callback::from(Box::new(|x: i64| x + 1) as Transform);
+/// assert!(callback.holds::<Transform>());
+/// let closure: &Transform = callback.try_as_ref().unwrap();
+/// assert_eq!(closure(1), 2);
+/// assert_eq!(Callback::TYPE_NAMES, ["Box<dyn Fn(i64) -> i64 + Send>", "fn() -> u32"]);
+/// ```
+///
+/// The derives `From`, `TryAsRef` and `TryAsMut` also accept tuple structs with
+/// exactly one field marked with `#[try_as(transparent)]`, deriving `From` in
+/// both directions, `AsRef` and `AsMut`, respectively:
+/// ```
+/// #[derive(try_as_macros::From, try_as_macros::TryAsRef, try_as_macros::TryAsMut)]
+/// #[try_as(transparent)]
+/// struct UserId(u64);
+///
+/// let mut id = UserId::from(7);
+/// *id.as_mut() += 1;
+/// assert_eq!(*id.as_ref(), 8);
+/// assert_eq!(u64::from(id), 8);
+/// ```
+///
+/// The implementations of `From`, `TryInto`, `IntoOption`, `TryAsRef` and `TryAsMut`
+/// for the type of a variant marked with `#[try_as(cfg(...))]` are only compiled
+/// if the predicate holds, like with `#[cfg(...)]`, while the variant itself stays:
+/// ```
+/// #[derive(try_as_macros::From)]
+/// enum Value {
+///     Number(i64),
+///     #[try_as(cfg(feature = "wide"))]
+///     Wide(i128),
+/// }
+///
+/// let value = Value::from(1i64);
+/// // Without the feature `wide`, `Value::from(1i128)` doesn't compile.
+/// let wide = Value::Wide(1);
+/// ```
+///
+/// For a variant marked with `#[try_as(validate = "path")]`, `TryFrom` is derived
+/// instead of `From`, calling the function at `path` with a reference to the value.
+/// If it returns `false`, the value is handed back as the error:
+/// ```
+/// fn is_port(port: &u16) -> bool {
+///     *port != 0
+/// }
+///
+/// #[derive(Debug, PartialEq, try_as_macros::From)]
+/// enum Setting {
+///     Name(String),
+///     #[try_as(validate = "is_port")]
+///     Port(u16),
+/// }
+///
+/// assert_eq!(Setting::try_from(8080), Ok(Setting::Port(8080)));
+/// assert_eq!(Setting::try_from(0), Err(0));
+/// ```
+///
+/// Variants of any other shape, e.g. with several fields or wrapping the value, can be
+/// marked with `#[try_as(with = "module")]`. The type of the variant is then given by
+/// `module::Target`, and the derives `From`, `TryInto`, `TryAsRef` and `TryAsMut` call
+/// the functions `from`, `into`, `as_ref` and `as_mut` of the module, respectively,
+/// which take the fields of the variant in declaration order. `TypedContainer`
+/// supports these variants as well, the other derives don't:
+/// ```
+/// use std::rc::Rc;
+/// use try_as_traits::{TryAsRef, TypedContainer};
+///
+/// #[derive(Debug, PartialEq, try_as_macros::From, try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::TypedContainer)]
+/// enum Token {
+///     Number(i64),
+///     #[try_as(with = "spanned_text")]
+///     Text { text: Rc<str>, start: usize },
+/// }
+///
+/// mod spanned_text {
+///     use std::rc::Rc;
+///
+///     pub type Target = Rc<str>;
+///
+///     pub fn from(text: Rc<str>) -> super::Token {
+///         super::Token::Text { text, start: 0 }
+///     }
+///
+///     pub fn into(text: Rc<str>, _start: usize) -> Rc<str> {
+///         text
+///     }
+///
+///     pub fn as_ref<'a>(text: &'a Rc<str>, _start: &usize) -> &'a Rc<str> {
+///         text
+///     }
+/// }
+///
+/// # fn main() {
+/// let token = Token::from(Rc::<str>::from("hi"));
+/// assert_eq!(token, Token::Text { text: "hi".into(), start: 0 });
+/// assert!(token.holds::<Rc<str>>());
+/// assert_eq!(TryAsRef::<Rc<str>>::try_as_ref(&token).map(|t| &**t), Some("hi"));
+/// let text: Result<Rc<str>, _> = token.try_into();
+/// assert_eq!(text.as_deref(), Ok("hi"));
+/// # }
+/// ```
+///
+/// To review the code generated by any of the derives, e.g. without `cargo expand`,
+/// mark the enum with `#[try_as(debug_expand)]`. Each derive then also generates a
+/// hidden associated constant `TRY_AS_EXPANDED_{DERIVE}` holding the code as a string,
+/// on one line, named like `TRY_AS_EXPANDED_TRY_AS_REF` for `TryAsRef`:
+/// ```
+/// #[derive(try_as_macros::From, try_as_macros::TryAsRef)]
+/// #[try_as(debug_expand)]
+/// enum Value {
+///     Number(i64),
+/// }
+///
+/// assert!(Value::TRY_AS_EXPANDED_FROM.starts_with("impl From < i64 > for Value"));
+/// assert!(Value::TRY_AS_EXPANDED_TRY_AS_REF.contains("TryAsRef < i64 >"));
+/// ```
+#[proc_macro_derive(From, attributes(try_as))]
 pub fn derive_from(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let enum_data = parse_enum_definition(&input);
-    gen_from_impls(&enum_data)
+    if let Some(newtype) = parse_transparent(&input) {
+        return debug_expand(&input, "From", gen_transparent_from(&newtype));
+    }
+    let (enum_data, with) = parse_enum_definition_with(&input);
+    let validators: Vec<_> = plain_variants(&input, variant_attrs(&input))
+        .into_iter()
+        .map(|attrs| {
+            attr_str(attrs, "try_as", "validate").map(|path| {
+                syn::parse_str::<syn::Path>(&path)
+                    .unwrap_or_else(|_| panic!("Expected a path for `validate`."))
+            })
+        })
+        .collect();
+    let cfgs = plain_variants(&input, variant_cfgs(&input));
+    debug_expand(
+        &input,
+        "From",
+        gen_from_impls(&enum_data, &cfgs, &validators, &with),
+    )
 }
 
 /// Derive [`TryInto<T>`] for a type enumerating enum.
-#[proc_macro_derive(TryInto)]
+///
+/// For an enum with a single variant, the conversion can't fail, so `From<Enum>`
+/// is derived for its type instead, and `TryInto` is provided by the standard
+/// library with the error type [`Infallible`](std::convert::Infallible):
+/// ```
+/// #[derive(try_as_macros::TryInto)]
+/// enum Celsius {
+///     Degrees(f64),
+/// }
+///
+/// let degrees: f64 = Celsius::Degrees(21.5).into();
+/// assert_eq!(degrees, 21.5);
+/// ```
+#[proc_macro_derive(TryInto, attributes(try_as))]
 pub fn derive_try_int(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let (enum_data, with) = parse_enum_definition_with(&input);
+    let cfgs = plain_variants(&input, variant_cfgs(&input));
+    debug_expand(
+        &input,
+        "TryInto",
+        gen_try_into_impl(&enum_data, &cfgs, &with),
+    )
+}
+
+/// Derive `TryFrom<&Enum>` for each of the types `T` of a type enumerating enum,
+/// returning a clone of the contained value. All types must implement [`Clone`].
+/// The error names the expected type and the found variant.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// #[derive(try_as_macros::TryFromRef)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let config = HashMap::from([("name", Value::String("try_as".to_owned()))]);
+/// let name = String::try_from(&config["name"]).unwrap();
+/// assert_eq!(name, "try_as");
+/// assert_eq!(i64::try_from(&config["name"]).unwrap_err().found, "String");
+/// ```
+#[proc_macro_derive(TryFromRef, attributes(try_as))]
+pub fn derive_try_from_ref(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "TryFromRef", gen_try_from_ref(&enum_data))
+}
+
+/// Derive an inherent `fn try_new<T: 'static>(value: T) -> Result<Self, T>` for a
+/// type enumerating enum, wrapping `value` if the enum can hold values of its type,
+/// and handing it back otherwise. Unlike [`From`](macro@From), this works for any
+/// `T`, e.g. in code generic over it. All types must be `'static`.
+///
+/// ```
+/// #[derive(Debug, PartialEq, try_as_macros::TryNew)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// fn wrap<T: 'static>(value: T) -> Option<Value> {
+///     Value::try_new(value).ok()
+/// }
+///
+/// assert_eq!(wrap(3i64), Some(Value::Number(3)));
+/// assert_eq!(wrap(3u8), None);
+/// ```
+#[proc_macro_derive(TryNew, attributes(try_as))]
+pub fn derive_try_new(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "TryNew", gen_try_new(&enum_data))
+}
+
+/// Derive [`From<Enum>`](From) for `Option<T>` for a type enumerating enum,
+/// for each of its types `T`.
+///
+/// ```
+/// #[derive(try_as_macros::IntoOption)]
+/// enum Value {
+///     Number(i64),
+///     Bool(bool),
+/// }
+///
+/// let number: Option<i64> = Value::Number(3).into();
+/// assert_eq!(number, Some(3));
+/// let number: Option<i64> = Value::Bool(false).into();
+/// assert_eq!(number, None);
+/// ```
+#[proc_macro_derive(IntoOption, attributes(try_as))]
+pub fn derive_into_option(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(
+        &input,
+        "IntoOption",
+        gen_into_option_impls(&enum_data, &variant_cfgs(&input)),
+    )
+}
+
+/// Derive [`Default`] for a type enumerating enum, holding the default value of
+/// the type of the variant marked with `#[try_as(default)]`.
+///
+/// ```
+/// #[derive(Debug, PartialEq, try_as_macros::Default)]
+/// enum Value {
+///     Number(i64),
+///     #[try_as(default)]
+///     Unit(()),
+/// }
+///
+/// assert_eq!(Value::default(), Value::Unit(()));
+/// ```
+#[proc_macro_derive(Default, attributes(try_as))]
+pub fn derive_default(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    let marked: Vec<_> = variant_attrs(&input)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, attrs)| attr_flag(attrs, "try_as", "default"))
+        .map(|(i, _)| i)
+        .collect();
+    debug_expand(
+        &input,
+        "Default",
+        match marked[..] {
+            [i] => gen_default(&enum_data, i),
+            [] => panic!("Mark the default variant with `#[try_as(default)]`."),
+            _ => panic!("Only one variant can be marked with `#[try_as(default)]`."),
+        },
+    )
+}
+
+/// Derive [`Hash`](std::hash::Hash) for a type enumerating enum, hashing what identifies
+/// the variant, and then the value it holds. The variant is identified by
+/// `#[try_as(hash_by = "...")]`:
+/// * `"index"`, the default: its position in the enum definition, as a `u32`. Stable
+///   as long as variants are only added at the end.
+/// * `"name"`: its identifier. Stable as long as variants aren't renamed.
+/// * `"type_id"`: the [`TypeId`](std::any::TypeId) of its type. Only stable within one build.
+///
+/// ```
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::{Hash, Hasher};
+///
+/// #[derive(try_as_macros::Hash)]
+/// #[try_as(hash_by = "name")]
+/// enum Value {
+///     Number(i64),
+///     Count(u64),
+/// }
+///
+/// let hash = |value: &Value| {
+///     let mut hasher = DefaultHasher::new();
+///     value.hash(&mut hasher);
+///     hasher.finish()
+/// };
+/// let mut hasher = DefaultHasher::new();
+/// ("Count", 1u64).hash(&mut hasher);
+/// assert_eq!(hash(&Value::Count(1)), hasher.finish());
+/// assert_ne!(hash(&Value::Count(1)), hash(&Value::Number(1)));
+/// ```
+#[proc_macro_derive(Hash, attributes(try_as))]
+pub fn derive_hash(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    let hash_by = attr_str(&input.attrs, "try_as", "hash_by");
+    debug_expand(
+        &input,
+        "Hash",
+        gen_hash(&enum_data, hash_by.as_deref().unwrap_or("index")),
+    )
+}
+
+/// Derive an inherent `fn sample_of_each() -> Vec<Self>` for a type enumerating enum,
+/// returning one value per variant, in declaration order. Each holds the default
+/// value of its type, unless the variant is marked with `#[try_as(sample = "...")]`,
+/// giving an expression to use instead.
+///
+/// ```
+/// #[derive(Debug, PartialEq, try_as_macros::SampleOfEach)]
+/// enum Value {
+///     Number(i64),
+///     #[try_as(sample = "std::num::NonZeroU8::MIN")]
+///     Count(std::num::NonZeroU8),
+/// }
+///
+/// assert_eq!(
+///     Value::sample_of_each(),
+///     [Value::Number(0), Value::Count(std::num::NonZeroU8::MIN)]
+/// );
+/// ```
+///
+/// With `#[try_as(test_roundtrip)]` on the enum, a test `try_as_roundtrip_{enum}` is
+/// generated as well, checking for each sample that `TypedContainer` and `TryAsRef`
+/// agree on the type it holds, and that `TryInto` and `From` convert it back and forth.
+/// Variants marked with `#[try_as(validate = "...")]` are converted back with `TryFrom`
+/// instead, which must accept their sample.
+/// The enum must implement those traits, e.g. by deriving them:
+/// ```
+/// use try_as_traits::TypedContainer;
+///
+/// #[derive(
+///     try_as_macros::SampleOfEach,
+///     try_as_macros::From,
+///     try_as_macros::TryInto,
+///     try_as_macros::TryAsRef,
+///     try_as_macros::TypedContainer,
+/// )]
+/// #[try_as(test_roundtrip)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+/// ```
+#[proc_macro_derive(SampleOfEach, attributes(try_as))]
+pub fn derive_sample_of_each(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let enum_data = parse_enum_definition(&input);
-    gen_try_into_impl(&enum_data)
+    let samples = variant_attrs(&input)
+        .into_iter()
+        .map(|attrs| {
+            attr_str(attrs, "try_as", "sample").map(|sample| {
+                syn::parse_str::<syn::Expr>(&sample)
+                    .unwrap_or_else(|_| panic!("Expected an expression for `sample`."))
+            })
+        })
+        .collect();
+    let validated: Vec<_> = variant_attrs(&input)
+        .into_iter()
+        .map(|attrs| attr_str(attrs, "try_as", "validate").is_some())
+        .collect();
+    debug_expand(
+        &input,
+        "SampleOfEach",
+        gen_sample_of_each(
+            &enum_data,
+            samples,
+            attr_flag(&input.attrs, "try_as", "test_roundtrip"),
+            &validated,
+        ),
+    )
 }
 
 /// Derive trait [`TryAsRef`] for a type enumerating enum.
-#[proc_macro_derive(TryAsRef)]
+///
+/// For an enum with a single variant, [`AsRef`] is derived for its type as well:
+/// ```
+/// use try_as_traits::{TryAsMut, TryAsRef};
+///
+/// #[derive(try_as_macros::TryAsRef, try_as_macros::TryAsMut)]
+/// enum Name {
+///     Text(String),
+/// }
+///
+/// let mut name = Name::Text("Ada".to_owned());
+/// name.as_mut().push_str(" Lovelace");
+/// let text: &String = name.as_ref();
+/// assert_eq!(text, "Ada Lovelace");
+/// ```
+///
+/// With `#[try_as(compact)]`, a single implementation for all `T: 'static` downcasts
+/// the value returned by `PayloadAny` instead of matching on the variants, see
+/// [`PayloadAny`]. It returns `None` for types the enum can't hold, instead of
+/// failing to compile.
+#[proc_macro_derive(TryAsRef, attributes(try_as))]
 pub fn derive_try_as_ref(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let enum_data = parse_enum_definition(&input);
-    gen_try_as_ref(&enum_data)
+    if let Some(newtype) = parse_transparent(&input) {
+        return debug_expand(&input, "TryAsRef", gen_transparent_as_ref(&newtype));
+    }
+    let (enum_data, with) = parse_enum_definition_with(&input);
+    let cfgs = plain_variants(&input, variant_cfgs(&input));
+    debug_expand(
+        &input,
+        "TryAsRef",
+        gen_try_as_ref(&enum_data, is_compact(&input), &cfgs, &with),
+    )
 }
 
 /// Derive trait [`TryAsMut`] for a type enumerating enum.
-#[proc_macro_derive(TryAsMut)]
+///
+/// For an enum with a single variant, [`AsMut`] is derived for its type as well,
+/// like [`AsRef`] by the `TryAsRef` derive.
+///
+/// With `#[try_as(compact)]`, a single implementation for all `T: 'static` downcasts
+/// the value returned by `PayloadAny`, like that of `TryAsRef`.
+#[proc_macro_derive(TryAsMut, attributes(try_as))]
 pub fn derive_try_as_mut(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let enum_data = parse_enum_definition(&input);
-    gen_try_as_mut(&enum_data)
+    if let Some(newtype) = parse_transparent(&input) {
+        return debug_expand(&input, "TryAsMut", gen_transparent_as_mut(&newtype));
+    }
+    let (enum_data, with) = parse_enum_definition_with(&input);
+    let cfgs = plain_variants(&input, variant_cfgs(&input));
+    debug_expand(
+        &input,
+        "TryAsMut",
+        gen_try_as_mut(&enum_data, is_compact(&input), &cfgs, &with),
+    )
 }
 
 /// Derive [`TypedContainer`] for a type enumerating enum.
-#[proc_macro_derive(TypedContainer)]
+///
+/// This also enables the strict accessors of `TryAsRefStrict`:
+/// ```
+/// use try_as_traits::{TryAsRef, TryAsRefStrict, TypedContainer};
+///
+/// #[derive(try_as_macros::TryAsRef, try_as_macros::TypedContainer)]
+/// enum Value {
+///     Number(i64),
+///     Bool(bool),
+/// }
+///
+/// let value = Value::Bool(false);
+/// assert_eq!(value.try_ref::<bool>(), Ok(&false));
+/// let err = value.try_ref::<i64>().unwrap_err();
+/// assert_eq!((err.expected, err.found), ("i64", "bool"));
+/// ```
+///
+/// With `#[try_as(compact)]`, `TypedContainer::type_id` asks `PayloadAny`
+/// instead of matching on the variants, see [`PayloadAny`].
+///
+/// Enums without variants are supported, like by the other derives; values of them
+/// can't exist, so the implementation is never called:
+/// ```
+/// use try_as_traits::{Introspect, TypedContainer};
+///
+/// #[derive(try_as_macros::TypedContainer, try_as_macros::Introspect)]
+/// enum Never {}
+///
+/// assert!(Never::TYPES.is_empty());
+/// ```
+#[proc_macro_derive(TypedContainer, attributes(try_as))]
 pub fn derive_typed_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let (enum_data, with) = parse_enum_definition_with(&input);
+    debug_expand(
+        &input,
+        "TypedContainer",
+        gen_typed_value(&enum_data, is_compact(&input), &with),
+    )
+}
+
+/// Derive [`TypedContainerMut`] for a type enumerating enum. All types must be `'static`.
+///
+/// ```
+/// use std::any::Any;
+/// use try_as_traits::{TypedContainer, TypedContainerMut};
+///
+/// #[derive(Debug, PartialEq, try_as_macros::TypedContainer, try_as_macros::TypedContainerMut)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let mut value = Value::Number(1);
+/// let old = value.replace_any(Box::new("Hello".to_owned())).unwrap();
+/// assert_eq!(old.downcast_ref::<i64>(), Some(&1));
+/// assert_eq!(value, Value::String("Hello".to_owned()));
+///
+/// let rejected = value.replace_any(Box::new(1.5f64)).unwrap_err();
+/// assert_eq!(rejected.downcast_ref::<f64>(), Some(&1.5));
+/// ```
+#[proc_macro_derive(TypedContainerMut, attributes(try_as))]
+pub fn derive_typed_container_mut(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(
+        &input,
+        "TypedContainerMut",
+        gen_typed_container_mut(&enum_data),
+    )
+}
+
+/// Derive [`PayloadAny`] for a type enumerating enum.
+///
+/// This is the one `match` on the variants that the derives of [`TryAsRef`],
+/// [`TryAsMut`] and [`TypedContainer`] share when the enum is marked with
+/// `#[try_as(compact)]`. `TryAsRef` and `TryAsMut` are then implemented once for all
+/// types, and `TypedContainer::type_id` without a `match`, which keeps the generated
+/// code small for enums with many variants, at the cost of a
+/// [`TypeId`](std::any::TypeId) comparison per access.
+/// ```
+/// use try_as_traits::{TryAsMut, TryAsRef, TypedContainer};
+///
+/// #[derive(
+///     try_as_macros::PayloadAny,
+///     try_as_macros::TryAsRef,
+///     try_as_macros::TryAsMut,
+///     try_as_macros::TypedContainer,
+/// )]
+/// #[try_as(compact)]
+/// enum Value {
+///     Number(i64),
+///     Bool(bool),
+/// }
+///
+/// let mut value = Value::Number(3);
+/// let number: &mut i64 = value.try_as_mut().unwrap();
+/// *number += 1;
+/// assert_eq!(value.try_as_ref(), Some(&4i64));
+/// assert_eq!(TryAsRef::<bool>::try_as_ref(&value), None);
+/// assert_eq!(TryAsRef::<String>::try_as_ref(&value), None);
+/// assert!(value.holds::<i64>());
+/// ```
+#[proc_macro_derive(PayloadAny, attributes(try_as))]
+pub fn derive_payload_any(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "PayloadAny", gen_payload_any(&enum_data))
+}
+
+/// Derive [`EqByInner<O>`] for a type enumerating enum and every type `O` implementing
+/// [`PayloadAny`], such as other enums deriving it. All types must implement
+/// `PartialEq` and be `'static`.
+///
+/// ```
+/// use try_as_traits::EqByInner;
+///
+/// #[derive(try_as_macros::EqByInner)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// #[derive(try_as_macros::PayloadAny)]
+/// enum WireValue {
+///     Number(i64),
+///     Bytes(Vec<u8>),
+/// }
+///
+/// assert!(Value::Number(1).eq_inner(&WireValue::Number(1)));
+/// assert!(!Value::Number(1).eq_inner(&WireValue::Number(2)));
+/// assert!(!Value::String("".to_owned()).eq_inner(&WireValue::Bytes(vec![])));
+/// ```
+#[proc_macro_derive(EqByInner, attributes(try_as))]
+pub fn derive_eq_by_inner(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "EqByInner", gen_eq_by_inner(&enum_data))
+}
+
+/// Derive [`TypeNames`] for a type enumerating enum. If the variants are marked with
+/// `#[try_as(tag = ...)]`, as for `Tagged`, the tags are part of the fingerprint.
+///
+/// ```
+/// use try_as_traits::TypeNames;
+///
+/// #[derive(try_as_macros::TypeNames)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+///     Bool(bool),
+/// }
+///
+/// assert_eq!(Value::type_names().join(", "), "i64, String, bool");
+/// assert_eq!(Value::VARIANT_NAMES, ["Number", "String", "Bool"]);
+///
+/// // Another version of the enum, holding different types.
+/// #[derive(try_as_macros::TypeNames)]
+/// enum OldValue {
+///     Number(i64),
+///     String(String),
+/// }
+/// assert_ne!(Value::TYPESET_FINGERPRINT, OldValue::TYPESET_FINGERPRINT);
+///
+/// // The same types, tagged differently.
+/// #[derive(try_as_macros::TypeNames)]
+/// enum Tagged {
+///     #[try_as(tag = 1)]
+///     Number(i64),
+///     #[try_as(tag = 2)]
+///     String(String),
+/// }
+/// #[derive(try_as_macros::TypeNames)]
+/// enum Retagged {
+///     #[try_as(tag = 1)]
+///     Number(i64),
+///     #[try_as(tag = 3)]
+///     String(String),
+/// }
+/// assert_ne!(Tagged::TYPESET_FINGERPRINT, OldValue::TYPESET_FINGERPRINT);
+/// assert_ne!(Tagged::TYPESET_FINGERPRINT, Retagged::TYPESET_FINGERPRINT);
+/// ```
+#[proc_macro_derive(TypeNames, attributes(try_as))]
+pub fn derive_type_names(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    let tags = variant_attrs(&input)
+        .into_iter()
+        .any(|attrs| attr_int::<u16>(attrs, "try_as", "tag").is_some())
+        .then(|| tags(&input));
+    debug_expand(
+        &input,
+        "TypeNames",
+        gen_type_names(&enum_data, tags.as_deref()),
+    )
+}
+
+/// Derive [`TypeSet`], and [`ContainsType<T>`](ContainsType) for each of the types
+/// `T` of a type enumerating enum. This allows comparing the types of enums at
+/// compile time with `try_as_traits::assert_same_typeset!`, and naming their types
+/// with `try_as_traits::variant_types!`.
+#[proc_macro_derive(TypeSet, attributes(try_as))]
+pub fn derive_type_set(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "TypeSet", gen_type_set(&enum_data))
+}
+
+/// Derive [`Introspect`] for a type enumerating enum.
+///
+/// ```
+/// use try_as_traits::Introspect;
+///
+/// #[derive(try_as_macros::Introspect)]
+/// enum Value {
+///     Number(i64),
+///     Bool(bool),
+/// }
+///
+/// let info = Value::Bool(true).info();
+/// assert_eq!((info.name, info.type_name, info.size), ("Bool", "bool", 1));
+/// assert_eq!(info.type_id, std::any::TypeId::of::<bool>());
+/// assert_eq!(Value::TYPES[0].align, std::mem::align_of::<i64>());
+/// assert_eq!(Value::Bool(true).size_of_inner(), 1);
+/// assert_eq!(Value::Number(1).align_of_inner(), std::mem::align_of::<i64>());
+/// assert_eq!(Value::info_of_type::<bool>().map(|info| info.name), Some("Bool"));
+/// assert_eq!(Value::index_of_type::<u8>(), None);
+///
+/// use try_as_traits::TypedIteratorExt;
+/// let values = [Value::Number(1), Value::Bool(true), Value::Number(2)];
+/// let counts = values.iter().count_types();
+/// assert_eq!(counts.as_slice(), [2, 1]);
+/// assert_eq!(counts.of::<bool>(), 1);
+/// ```
+///
+/// For a `#[non_exhaustive]` enum, `Introspect::NON_EXHAUSTIVE` is `true`:
+/// ```
+/// use try_as_traits::Introspect;
+///
+/// #[derive(try_as_macros::Introspect)]
+/// #[non_exhaustive]
+/// enum Value {
+///     Number(i64),
+/// }
+///
+/// assert!(Value::NON_EXHAUSTIVE);
+/// assert!(<&Value>::NON_EXHAUSTIVE);
+/// ```
+///
+/// With `#[try_as(type_index)]`, `Introspect::index_of_type` looks up the type in a
+/// [`TypeIndex`](try_as_traits::TypeIndex) built on first use instead of searching
+/// `TYPES` linearly, which pays off for enums with many variants. This speeds up the
+/// collections of `try_as_traits::collections` as well:
+/// ```
+/// use try_as_traits::Introspect;
+///
+/// #[derive(try_as_macros::Introspect)]
+/// #[try_as(type_index)]
+/// enum Value {
+///     Number(i64),
+///     Bool(bool),
+/// }
+///
+/// assert_eq!(Value::index_of_type::<bool>(), Some(1));
+/// assert_eq!(Value::index_of_type::<u8>(), None);
+/// ```
+#[proc_macro_derive(Introspect, attributes(try_as))]
+pub fn derive_introspect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(
+        &input,
+        "Introspect",
+        gen_introspect(
+            &enum_data,
+            is_non_exhaustive(&input),
+            attr_flag(&input.attrs, "try_as", "type_index"),
+        ),
+    )
+}
+
+/// Derive [`Instantiate`](try_as_traits::Instantiate) for a type enumerating enum
+/// that implements `Introspect`. Variants whose type implements [`Default`] can be
+/// instantiated, the others fail with `InstantiateError::NoDefault`.
+///
+/// ```
+/// use try_as_traits::Instantiate;
+///
+/// #[derive(try_as_macros::Introspect, try_as_macros::Instantiate)]
+/// enum Value {
+///     Number(i64),
+///     Stdout(std::io::Stdout),
+/// }
+///
+/// assert!(matches!(Value::instantiate(0), Ok(Value::Number(0))));
+/// assert!(Value::instantiate(1).is_err());
+/// ```
+#[proc_macro_derive(Instantiate, attributes(try_as))]
+pub fn derive_instantiate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Instantiate", gen_instantiate(&enum_data))
+}
+
+/// Derive an inherent `const fn discriminant(&self)` for a type enumerating enum with a
+/// primitive representation like `#[repr(u8)]`, returning the discriminant of the
+/// variant as declared, and the table `DISCRIMINANTS` of all of them, in declaration
+/// order like `Introspect::TYPES`. Variants without an explicit discriminant follow
+/// the previous one, like in Rust.
+///
+/// ```
+/// #[derive(try_as_macros::Discriminant)]
+/// #[repr(u8)]
+/// enum Value {
+///     Number(i64) = 1,
+///     Float(f64),
+///     String(String) = 10,
+/// }
+///
+/// assert_eq!(Value::Float(0.5).discriminant(), 2);
+/// assert_eq!(Value::DISCRIMINANTS, [1, 2, 10]);
+/// ```
+#[proc_macro_derive(Discriminant, attributes(try_as))]
+pub fn derive_discriminant(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    let repr = repr_int(&input)
+        .unwrap_or_else(|| panic!("`Discriminant` requires a representation like `#[repr(u8)]`."));
+    debug_expand(
+        &input,
+        "Discriminant",
+        gen_discriminant(&enum_data, &repr, &discriminants(&input)),
+    )
+}
+
+/// Derive [`Describe`] for a type enumerating enum. The `Debug` rendering of the
+/// contained value is included if its type implements `Debug`.
+///
+/// ```
+/// use try_as_traits::Describe;
+///
+/// struct Opaque;
+///
+/// #[derive(try_as_macros::Describe)]
+/// enum Value {
+///     Number(i64),
+///     Opaque(Opaque),
+/// }
+///
+/// assert_eq!(Value::Number(3).describe().to_string(), "Number(i64) = 3");
+/// assert_eq!(Value::Opaque(Opaque).describe().value, None);
+/// ```
+#[proc_macro_derive(Describe, attributes(try_as))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Describe", gen_describe(&enum_data))
+}
+
+/// Derive [`VariantName`] for a type enumerating enum.
+///
+/// Together with [`TryInto`](macro@TryInto), this enables the context-rich
+/// conversions of `TryIntoCtx`:
+/// ```
+/// use try_as_traits::TryIntoCtx;
+///
+/// #[derive(try_as_macros::TryInto, try_as_macros::VariantName, Debug)]
+/// enum Value {
+///     Number(i64),
+///     Bool(bool),
+/// }
+///
+/// let err = Value::Bool(true).try_into_ctx::<i64>().unwrap_err();
+/// assert_eq!(err.expected, "i64");
+/// assert_eq!(err.found, "Bool");
+/// assert_eq!(err.value.as_deref(), Some("Bool(true)"));
+/// ```
+#[proc_macro_derive(VariantName, attributes(try_as))]
+pub fn derive_variant_name(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "VariantName", gen_variant_name(&enum_data))
+}
+
+/// Derive [`Tagged`] for a type enumerating enum. Every variant must be marked with
+/// a tag, unique within the enum, with `#[try_as(tag = ...)]`.
+///
+/// ```
+/// use try_as_traits::Tagged;
+///
+/// #[derive(try_as_macros::Tagged)]
+/// enum Value {
+///     #[try_as(tag = 2)]
+///     String(String),
+///     #[try_as(tag = 1)]
+///     Number(i64),
+/// }
+///
+/// assert_eq!(Value::Number(3).tag(), 1);
+/// assert_eq!(Value::TAGS, [2, 1]);
+/// assert_eq!(Value::variant_index_of_tag(1), Ok(1));
+/// assert!(Value::variant_index_of_tag(3).is_err());
+/// ```
+#[proc_macro_derive(Tagged, attributes(try_as))]
+pub fn derive_tagged(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Tagged", gen_tagged(&enum_data, &tags(&input)))
+}
+
+/// Derive `encoding::WireEncode` for a type enumerating enum, encoding the tag of the
+/// variant given by `#[try_as(tag = ...)]`, see [`Tagged`](macro@Tagged), followed by
+/// the value. All types must implement `WireEncode`.
+#[proc_macro_derive(WireEncode, attributes(try_as))]
+pub fn derive_wire_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(
+        &input,
+        "WireEncode",
+        gen_wire_encode(&enum_data, &tags(&input)),
+    )
+}
+
+/// Derive `encoding::WireDecode` for a type enumerating enum, reading the encoding
+/// written by [`WireEncode`](macro@WireEncode). All types must implement `WireDecode`.
+#[proc_macro_derive(WireDecode, attributes(try_as))]
+pub fn derive_wire_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(
+        &input,
+        "WireDecode",
+        gen_wire_decode(&enum_data, &tags(&input)),
+    )
+}
+
+/// Derive [`Registrable`] for a type enumerating enum.
+///
+/// ```
+/// use try_as_traits::Registry;
+///
+/// #[derive(try_as_macros::Registrable, Debug, PartialEq)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.register::<Value>();
+///
+/// let names: Vec<_> = registry.entries::<Value>().unwrap().iter().map(|e| e.type_name).collect();
+/// assert_eq!(names, ["i64", "String"]);
+///
+/// let value = registry.construct::<Value>(Box::new(4i64));
+/// assert_eq!(value.unwrap(), Value::Number(4));
+/// assert!(registry.construct::<Value>(Box::new(false)).is_err());
+/// ```
+#[proc_macro_derive(Registrable, attributes(try_as))]
+pub fn derive_registrable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Registrable", gen_registrable(&enum_data))
+}
+
+/// Derive an associated constant `CONSTRUCTORS` for a type enumerating enum,
+/// mapping the [`std::any::TypeId`] of each type to a function that wraps a
+/// type-erased value of that type into the enum.
+///
+/// ```
+/// use std::any::{Any, TypeId};
+///
+/// #[derive(try_as_macros::Constructors, Debug, PartialEq)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let erased: Box<dyn Any> = Box::new("Hello".to_owned());
+/// let (_, construct) = Value::CONSTRUCTORS
+///     .iter()
+///     .find(|(type_id, _)| *type_id == (*erased).type_id())
+///     .unwrap();
+/// assert_eq!(construct(erased).unwrap(), Value::String("Hello".to_owned()));
+/// ```
+#[proc_macro_derive(Constructors, attributes(try_as))]
+pub fn derive_constructors(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Constructors", gen_constructors(&enum_data))
+}
+
+/// Derive a method `try_from_any_ref` for a type enumerating enum, which builds the
+/// enum from a borrowed type-erased value by cloning it. All types must implement `Clone`.
+///
+/// ```
+/// use std::any::Any;
+///
+/// #[derive(try_as_macros::TryFromAnyRef, Debug, PartialEq)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let erased: &dyn Any = &"Hello".to_owned();
+/// assert_eq!(Value::try_from_any_ref(erased), Some(Value::String("Hello".to_owned())));
+/// assert_eq!(Value::try_from_any_ref(&1.5f64), None);
+/// ```
+///
+/// The method is `#[must_use]`, unless the enum is marked with `#[try_as(no_must_use)]`.
+///
+/// With `#[try_as(type_index)]`, the type of the value is looked up in a
+/// [`TypeIndex`](try_as_traits::TypeIndex) built on first use instead of attempting
+/// to downcast to each type in turn, which pays off for enums with many variants:
+/// ```
+/// #[derive(try_as_macros::TryFromAnyRef, Debug, PartialEq)]
+/// #[try_as(type_index)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// assert_eq!(Value::try_from_any_ref(&2i64), Some(Value::Number(2)));
+/// assert_eq!(Value::try_from_any_ref(&1.5f64), None);
+/// ```
+#[proc_macro_derive(TryFromAnyRef, attributes(try_as))]
+pub fn derive_try_from_any_ref(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(
+        &input,
+        "TryFromAnyRef",
+        gen_try_from_any_ref(
+            &enum_data,
+            &must_use(&input),
+            attr_flag(&input.attrs, "try_as", "type_index"),
+        ),
+    )
+}
+
+/// Derive `From<Enum>` for `Box<dyn Any + Send + Sync>` for a type enumerating enum,
+/// boxing the contained value (not the enum). All types must be `Send + Sync + 'static`.
+///
+/// ```
+/// use std::any::Any;
+///
+/// #[derive(try_as_macros::IntoBoxedAny)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let erased: Box<dyn Any + Send + Sync> = Value::Number(3).into();
+/// assert_eq!(erased.downcast_ref::<i64>(), Some(&3));
+/// ```
+#[proc_macro_derive(IntoBoxedAny, attributes(try_as))]
+pub fn derive_into_boxed_any(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "IntoBoxedAny", gen_into_boxed_any(&enum_data))
+}
+
+/// Derive `From<Enum>` for `Box<dyn Error + Send + Sync>` for a type enumerating
+/// enum, boxing the contained value (not the enum), and a method `try_downcast`
+/// converting such a box back into the enum. All types must implement
+/// `std::error::Error + Send + Sync + 'static`.
+///
+/// ```
+/// use std::error::Error;
+///
+/// #[derive(Debug, try_as_macros::IntoBoxedError)]
+/// enum ConfigError {
+///     Io(std::io::Error),
+///     Number(std::num::ParseIntError),
+/// }
+///
+/// let error = "x".parse::<i64>().unwrap_err();
+/// let boxed: Box<dyn Error + Send + Sync> = ConfigError::Number(error.clone()).into();
+/// assert_eq!(boxed.to_string(), error.to_string());
+/// assert!(matches!(ConfigError::try_downcast(boxed), Ok(ConfigError::Number(_))));
+///
+/// let other: Box<dyn Error + Send + Sync> = "something else".into();
+/// assert!(ConfigError::try_downcast(other).is_err());
+/// ```
+#[proc_macro_derive(IntoBoxedError, attributes(try_as))]
+pub fn derive_into_boxed_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "IntoBoxedError", gen_into_boxed_error(&enum_data))
+}
+
+/// Derive `Display` and `std::error::Error` for a type enumerating enum whose types
+/// are all errors. Both forward to the contained error, including `source`, so the
+/// enum is transparent in error reports.
+///
+/// ```
+/// use std::error::Error;
+///
+/// #[derive(Debug, try_as_macros::ErrorEnum, try_as_macros::From)]
+/// enum ConfigError {
+///     Io(std::io::Error),
+///     Number(std::num::ParseIntError),
+/// }
+///
+/// fn parse(s: &str) -> Result<i64, ConfigError> {
+///     Ok(s.parse::<i64>()?)
+/// }
+///
+/// let error = parse("x").unwrap_err();
+/// assert_eq!(error.to_string(), "invalid digit found in string");
+/// assert!(error.source().is_none());
+/// ```
+#[proc_macro_derive(ErrorEnum, attributes(try_as))]
+pub fn derive_error_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "ErrorEnum", gen_error_enum(&enum_data))
+}
+
+/// Derive methods returning trait objects of the contained value for a type
+/// enumerating enum. The traits are listed in the attribute `#[try_as(dyn(...))]`,
+/// and each must be implemented by all types. For a trait `Display`, the methods
+/// `as_dyn_display` and `as_dyn_display_mut` are derived.
+///
+/// Traits listed in `#[try_as(into_dyn(...))]` get a consuming conversion into a
+/// boxed trait object instead, e.g. `into_dyn_write` for `Write`. This requires
+/// all types to be `'static`.
+///
+/// ```
+/// use std::fmt::Debug;
+/// use std::io::Write;
+///
+/// #[derive(try_as_macros::AsDyn)]
+/// #[try_as(dyn(Debug), into_dyn(Write))]
+/// enum Value {
+///     Bytes(Vec<u8>),
+///     Sink(std::io::Sink),
+/// }
+///
+/// let value = Value::Bytes(vec![1, 2]);
+/// assert_eq!(format!("{:?}", value.as_dyn_debug()), "[1, 2]");
+///
+/// let mut writer: Box<dyn Write> = Value::Sink(std::io::sink()).into_dyn_write();
+/// writer.write_all(b"Hello").unwrap();
+/// ```
+///
+/// The `into_dyn_*` methods are `#[must_use]`, unless the enum is marked with
+/// `#[try_as(no_must_use)]`.
+#[proc_macro_derive(AsDyn, attributes(try_as))]
+pub fn derive_as_dyn(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(
+        &input,
+        "AsDyn",
+        gen_as_dyn(
+            &enum_data,
+            &attr_paths(&input.attrs, "dyn"),
+            &attr_paths(&input.attrs, "into_dyn"),
+            &must_use(&input),
+        ),
+    )
+}
+
+/// Derive a storage `{Enum}Columns` for a type enumerating enum, which keeps the
+/// values of each type in a dense column of its own, and implements [`DenseColumn`]
+/// for each of them.
+///
+/// ```
+/// #[derive(try_as_macros::Columns)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let mut columns = ValueColumns::new();
+/// columns.insert(Value::Number(1));
+/// columns.insert(Value::String("Hello".to_owned()));
+/// columns.insert(Value::Number(2));
+///
+/// assert_eq!(columns.iter::<i64>().sum::<i64>(), 3);
+/// assert_eq!(columns.len(), 3);
+/// ```
+///
+/// The storage has the visibility of the enum. Its name and its visibility can be
+/// chosen with `#[try_as(columns_name = "...", columns_vis = "...")]`, and
+/// `#[try_as(columns_module = "...")]` places it in a module of its own, next
+/// to the enum. So does the module shared by the types generated for the enum,
+/// `#[try_as(module = "...")]`: as a module can only be defined once, `Columns` is
+/// the derive that generates it, and the others need modules of their own.
+/// `#[try_as(columns_derive(...))]` adds derives to the storage, next to `Default`:
+/// ```
+/// #[derive(try_as_macros::Columns)]
+/// #[try_as(columns_name = "Table", columns_vis = "pub(crate)", columns_module = "value_gen")]
+/// #[try_as(columns_derive(Debug, Clone))]
+/// pub enum Value {
+///     Number(i64),
+/// }
+///
+/// fn main() {
+///     let table = value_gen::Table::new();
+///     assert!(table.clone().is_empty());
+///     assert!(format!("{:?}", table).starts_with("Table"));
+/// }
+/// ```
+#[proc_macro_derive(Columns, attributes(try_as))]
+pub fn derive_columns(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    let columns = companion(&input, "columns", "Columns");
+    debug_expand(
+        &input,
+        "Columns",
+        in_module(gen_columns(&enum_data, &columns), &columns, &input.vis),
+    )
+}
+
+/// Derive a `#[repr(C)]` tagged union `{Enum}Ffi` for a type enumerating enum, to
+/// pass its values across a C boundary. It consists of the index of the variant in
+/// declaration order, `tag`, and a `#[repr(C)]` union `{Enum}FfiPayload` with one
+/// field per variant, named after the variant in snake case. The enum converts into
+/// it with `From`, and back with the `unsafe` method `into_enum`, which fails with an
+/// [`InvalidTag`] if the tag doesn't name a variant.
+///
+/// All types must be `Copy` and should be FFI-safe. Converting back is `unsafe`, as
+/// the fields are public for the C side to fill in: the caller must make sure that
+/// the payload is valid for its tag, e.g. by only handing back values created by
+/// `From`.
+///
+/// The struct has the visibility of the enum. Its name, its visibility and its
+/// module can be chosen with `#[try_as(ffi_name = "...", ffi_vis = "...",
+/// ffi_module = "...")]` like those of [`Columns`], which generates the shared
+/// `#[try_as(module = "...")]`. The union is named after the struct and placed next
+/// to it:
+/// ```
+/// #[derive(try_as_macros::Ffi, try_as_macros::Columns)]
+/// #[try_as(module = "value_gen", ffi_module = "value_ffi")]
+/// enum Value {
+///     Number(i64),
+/// }
+///
+/// fn main() {
+///     let ffi = value_ffi::ValueFfi::from(Value::Number(1));
+///     let payload: value_ffi::ValueFfiPayload = ffi.payload;
+///     let columns = value_gen::ValueColumns::new();
+/// #   let _ = (payload, columns);
+/// }
+/// ```
+/// Without `ffi_module`, the derive rejects the shared module rather than
+/// defining it a second time:
+/// ```compile_fail
+/// #[derive(try_as_macros::Ffi, try_as_macros::Columns)]
+/// #[try_as(module = "value_gen")]
+/// enum Value {
+///     Number(i64),
+/// }
+/// ```
+/// ```
+/// #[derive(Debug, PartialEq, try_as_macros::Ffi)]
+/// enum Value {
+///     Number(i64),
+///     Bool(bool),
+/// }
+///
+/// let ffi = ValueFfi::from(Value::Bool(true));
+/// assert_eq!(ffi.tag, 1);
+/// // Safety: `ffi` was created by `From`.
+/// assert_eq!(unsafe { ffi.into_enum() }, Ok(Value::Bool(true)));
+///
+/// let ffi = ValueFfi { tag: 7, payload: ValueFfiPayload { number: 0 } };
+/// // Safety: the tag doesn't name a variant, so the payload isn't read.
+/// assert_eq!(unsafe { ffi.into_enum() }.unwrap_err().tag, 7);
+/// ```
+///
+/// Fields named after a keyword are raw identifiers, except for those that can't be,
+/// like `super`, which get a trailing underscore:
+/// ```
+/// #[derive(Debug, PartialEq, try_as_macros::Ffi)]
+/// enum Token {
+///     Type(u8),
+///     Fn(u32),
+///     Super(bool),
+/// }
+///
+/// let ffi = TokenFfi::from(Token::Fn(3));
+/// // Safety: `ffi` was created by `From`.
+/// assert_eq!(unsafe { ffi.payload.r#fn }, 3);
+/// let ffi = TokenFfi { tag: 2, payload: TokenFfiPayload { super_: true } };
+/// // Safety: the payload holds the field of the variant named by the tag.
+/// assert_eq!(unsafe { ffi.into_enum() }, Ok(Token::Super(true)));
+/// ```
+///
+/// Safe code can build a value whose payload doesn't match its tag, but it can't
+/// convert such a value into the enum:
+/// ```compile_fail,E0133
+/// # #[derive(Debug, PartialEq, try_as_macros::Ffi)]
+/// # enum Value {
+/// #     Number(i64),
+/// #     Bool(bool),
+/// # }
+/// let ffi = ValueFfi { tag: 1, payload: ValueFfiPayload { number: 2 } };
+/// let value = ffi.into_enum();
+/// ```
+/// ```compile_fail,E0277
+/// # #[derive(Debug, PartialEq, try_as_macros::Ffi)]
+/// # enum Value {
+/// #     Number(i64),
+/// #     Bool(bool),
+/// # }
+/// let ffi = ValueFfi { tag: 1, payload: ValueFfiPayload { number: 2 } };
+/// let value = Value::try_from(ffi);
+/// ```
+#[proc_macro_derive(Ffi, attributes(try_as))]
+pub fn derive_ffi(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    let ffi = companion(&input, "ffi", "Ffi");
+    if !ffi.derives.is_empty() {
+        panic!("`ffi_derive` isn't supported, as most traits can't be derived for unions.");
+    }
+    debug_expand(
+        &input,
+        "Ffi",
+        in_module(gen_ffi(&enum_data, &ffi.ident, &ffi.vis), &ffi, &input.vis),
+    )
+}
+
+/// Derive [`Navigate`] for a type enumerating enum. Variants holding a `Vec` of the
+/// enum are treated as lists, variants holding a `HashMap` or `BTreeMap` from
+/// `String` to the enum as maps, and all others as leaves.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use try_as_traits::Navigate;
+///
+/// #[derive(try_as_macros::TryAsRef, try_as_macros::Navigate)]
+/// enum Value {
+///     Number(i64),
+///     List(Vec<Value>),
+///     Map(HashMap<String, Value>),
+/// }
+///
+/// let ports = Value::List(vec![Value::Number(80), Value::Number(443)]);
+/// let config = Value::Map(HashMap::from([("ports".to_owned(), ports)]));
+///
+/// assert_eq!(config.get_path_as::<i64>("ports[1]"), Some(&443));
+/// assert!(config.get_path("ports[2]").is_none());
+/// assert!(config.get_path("ports.http").is_none());
+/// ```
+#[proc_macro_derive(Navigate, attributes(try_as))]
+pub fn derive_navigate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Navigate", gen_navigate(&enum_data))
+}
+
+/// Derive [`PrettyPrint`](try_as_traits::pretty::PrettyPrint) for a type enumerating
+/// enum. Lists and maps of the enum are recognized like by [`Navigate`](macro@Navigate),
+/// and their values are rendered on the following lines, indented. The entries of maps
+/// are sorted by key. All other types are rendered with their `Debug` implementation.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use try_as_traits::pretty::{PrettyOptions, PrettyPrint};
+///
+/// #[derive(try_as_macros::PrettyPrint)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+///     List(Vec<Value>),
+///     Map(HashMap<String, Value>),
+/// }
+///
+/// let ports = Value::List(vec![Value::Number(80), Value::Number(443)]);
+/// let name = Value::String("gateway".to_owned());
+/// let config = Value::Map(HashMap::from([("ports".to_owned(), ports), ("name".to_owned(), name)]));
+///
+/// assert_eq!(
+///     config.pretty_print(&PrettyOptions::default()),
+///     r#"Map: HashMap<String,Value> (2)
+///   "name": String: String = "gateway"
+///   "ports": List: Vec<Value> (2)
+///     [0]: Number: i64 = 80
+///     [1]: Number: i64 = 443"#
+/// );
+///
+/// let options = PrettyOptions { max_depth: 1, max_width: 4, ..PrettyOptions::default() };
+/// assert_eq!(
+///     config.pretty_print(&options),
+///     r#"Map: HashMap<String,Value> (2)
+///   "name": String: String = "gat…
+///   "ports": List: Vec<Value> (2) …"#
+/// );
+/// ```
+#[proc_macro_derive(PrettyPrint, attributes(try_as))]
+pub fn derive_pretty_print(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "PrettyPrint", gen_pretty_print(&enum_data))
+}
+
+/// Derive [`Diff`](try_as_traits::diff::Diff) for a type enumerating enum. Lists and
+/// maps of the enum are recognized like by [`Navigate`](macro@Navigate), and compared
+/// entry by entry. All other types are compared with `PartialEq`.
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use try_as_traits::diff::{Diff, ValueDiff};
+///
+/// #[derive(try_as_macros::Diff)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+///     List(Vec<Value>),
+///     Map(BTreeMap<String, Value>),
+/// }
+///
+/// let config = |port, host: Value| {
+///     let ports = Value::List(vec![Value::Number(80), Value::Number(port)]);
+///     Value::Map(BTreeMap::from([("ports".to_owned(), ports), ("host".to_owned(), host)]))
+/// };
+/// let old = config(443, Value::String("example.org".to_owned()));
+/// let new = config(8443, Value::Number(0));
+///
+/// assert!(old.diff(&old).is_same());
+/// assert_eq!(
+///     old.diff(&new),
+///     ValueDiff::Nested(vec![
+///         ("host".to_owned(), ValueDiff::TypeChanged { from: "String", to: "Number" }),
+///         ("ports".to_owned(), ValueDiff::Nested(vec![("[1]".to_owned(), ValueDiff::ValueChanged)])),
+///     ])
+/// );
+/// ```
+#[proc_macro_derive(Diff, attributes(try_as))]
+pub fn derive_diff(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Diff", gen_diff(&enum_data))
+}
+
+/// Derive [`Merge`](try_as_traits::merge::Merge) for a type enumerating enum. Lists and
+/// maps of the enum are recognized like by [`Navigate`](macro@Navigate).
+///
+/// ```
+/// use std::collections::HashMap;
+/// use try_as_traits::merge::{Merge, MergeStrategy};
+///
+/// #[derive(Debug, PartialEq, try_as_macros::Merge)]
+/// enum Value {
+///     Number(i64),
+///     List(Vec<Value>),
+///     Map(HashMap<String, Value>),
+/// }
+///
+/// let map = |entries: Vec<(&str, Value)>| {
+///     Value::Map(entries.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+/// };
+/// let defaults = map(vec![
+///     ("port", Value::Number(80)),
+///     ("limits", map(vec![("cpu", Value::Number(1)), ("memory", Value::Number(512))])),
+///     ("hosts", Value::List(vec![Value::Number(1)])),
+/// ]);
+/// let overrides = map(vec![
+///     ("limits", map(vec![("memory", Value::Number(1024))])),
+///     ("hosts", Value::List(vec![Value::Number(2)])),
+/// ]);
+///
+/// assert_eq!(
+///     defaults.merge(overrides, MergeStrategy::ConcatLists),
+///     map(vec![
+///         ("port", Value::Number(80)),
+///         ("limits", map(vec![("cpu", Value::Number(1)), ("memory", Value::Number(1024))])),
+///         ("hosts", Value::List(vec![Value::Number(1), Value::Number(2)])),
+///     ])
+/// );
+/// ```
+#[proc_macro_derive(Merge, attributes(try_as))]
+pub fn derive_merge(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "Merge", gen_merge(&enum_data))
+}
+
+/// Derive [`DeepSize`](try_as_traits::deep_size::DeepSize) for a type enumerating enum,
+/// counting the heap allocations of the contained value. All types must implement
+/// `DeepSize`, which includes the enum itself, so variants can hold nested values.
+///
+/// ```
+/// use std::mem::size_of;
+/// use try_as_traits::deep_size::DeepSize;
+///
+/// #[derive(try_as_macros::DeepSize)]
+/// enum Expr {
+///     Number(i64),
+///     Negate(Box<Expr>),
+/// }
+///
+/// let expr = Expr::Negate(Box::new(Expr::Number(1)));
+/// assert_eq!(expr.deep_size(), 2 * size_of::<Expr>());
+/// ```
+#[proc_macro_derive(DeepSize, attributes(try_as))]
+pub fn derive_deep_size(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    debug_expand(&input, "DeepSize", gen_deep_size(&enum_data))
+}
+
+/// Derive [`Index<&str>`](std::ops::Index) and [`Index<usize>`](std::ops::Index) for a
+/// type enumerating enum that implements [`Navigate`], indexing into the children
+/// of maps and lists, respectively.
+///
+/// Indexing a value without the child panics, unless a variant holding `()` is
+/// marked with `#[try_as(null)]`. Then a value of that variant is returned instead,
+/// like with `serde_json::Value`. This requires the enum to be `Sync`.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// #[derive(Debug, PartialEq, try_as_macros::Navigate, try_as_macros::Index)]
+/// enum Value {
+///     #[try_as(null)]
+///     Null(()),
+///     Number(i64),
+///     List(Vec<Value>),
+///     Map(HashMap<String, Value>),
+/// }
+///
+/// let ports = Value::List(vec![Value::Number(80), Value::Number(443)]);
+/// let config = Value::Map(HashMap::from([("ports".to_owned(), ports)]));
+///
+/// assert_eq!(config["ports"][1], Value::Number(443));
+/// assert_eq!(config["hosts"][0], Value::Null(()));
+/// ```
+#[proc_macro_derive(Index, attributes(try_as))]
+pub fn derive_index(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_data = parse_enum_definition(&input);
+    let null = variant_attrs(&input)
+        .into_iter()
+        .position(|attrs| attr_flag(attrs, "try_as", "null"));
+    debug_expand(&input, "Index", gen_index(&enum_data, null))
+}
+
+/// Derive a method `into_parts` for a type enumerating enum, returning a tuple with
+/// one `Option` per variant, exactly one of which is `Some`.
+///
+/// ```
+/// #[derive(try_as_macros::IntoParts)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+///     Bool(bool),
+/// }
+///
+/// let (number, string, boolean) = Value::Bool(true).into_parts();
+/// assert_eq!(number, None);
+/// assert_eq!(string, None);
+/// assert_eq!(boolean, Some(true));
+/// ```
+///
+/// The method is `#[must_use]`, unless the enum is marked with `#[try_as(no_must_use)]`.
+#[proc_macro_derive(IntoParts, attributes(try_as))]
+pub fn derive_into_parts(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let enum_data = parse_enum_definition(&input);
-    gen_typed_value(&enum_data)
+    debug_expand(
+        &input,
+        "IntoParts",
+        gen_into_parts(&enum_data, &must_use(&input)),
+    )
 }
 
 fn parse_enum_definition(input: &DeriveInput) -> EnumData {
+    let (enum_data, with) = parse_enum_definition_with(input);
+    if !with.is_empty() {
+        panic!("`#[try_as(with = ...)]` is only supported by the derives From, TryInto, TryAsRef, TryAsMut and TypedContainer.");
+    }
+    enum_data
+}
+
+/// A variant marked with `#[try_as(with = "module")]`, holding a value of type
+/// `module::Target` in fields of any shape, converted by the functions of `module`.
+struct WithVariant {
+    ident: Ident,
+    module: syn::Path,
+    /// The pattern binding the fields of the variant, e.g. `(a0, a1)` or `{ text, start }`.
+    pattern: proc_macro2::TokenStream,
+    /// The names bound by `pattern`, in declaration order.
+    bindings: Vec<Ident>,
+    cfg: proc_macro2::TokenStream,
+}
+
+/// Returns the module given by `#[try_as(with = "module")]` among `attrs`.
+fn with_module(attrs: &[syn::Attribute]) -> Option<syn::Path> {
+    attr_str(attrs, "try_as", "with").map(|module| {
+        syn::parse_str(&module).unwrap_or_else(|_| panic!("Expected a path for `with`."))
+    })
+}
+
+/// Drops the entries of the variants marked with `#[try_as(with = ...)]` from
+/// `per_variant`, given in declaration order, like the variants of [`EnumData`].
+fn plain_variants<T>(input: &DeriveInput, per_variant: Vec<T>) -> Vec<T> {
+    variant_attrs(input)
+        .into_iter()
+        .zip(per_variant)
+        .filter(|(attrs, _)| with_module(attrs).is_none())
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+/// Like [`parse_enum_definition`], but returns the variants marked with
+/// `#[try_as(with = ...)]` separately instead of rejecting them.
+fn parse_enum_definition_with(input: &DeriveInput) -> (EnumData, Vec<WithVariant>) {
     // Make sure we have no generics
     if input.generics.type_params().count() > 0 {
         panic!("Type parameters aren't supported.");
@@ -79,7 +1613,33 @@ fn parse_enum_definition(input: &DeriveInput) -> EnumData {
     // Use to make sure that each type appears at most once
     let mut all_variant_types = HashSet::new();
     let mut variants: Vec<(Ident, Type)> = Vec::new();
-    for variant in data.variants.iter() {
+    let mut with = Vec::new();
+    let cfgs = variant_cfgs(input);
+    for (variant, cfg) in data.variants.iter().zip(cfgs) {
+        if let Some(module) = with_module(&variant.attrs) {
+            let bindings: Vec<Ident> = variant
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match &field.ident {
+                    Some(ident) => ident.clone(),
+                    None => Ident::new(&format!("a{}", i), Span::call_site()),
+                })
+                .collect();
+            let pattern = match &variant.fields {
+                Fields::Unit => quote! {},
+                Fields::Named(_) => quote! { { #(#bindings),* } },
+                Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+            };
+            with.push(WithVariant {
+                ident: variant.ident.clone(),
+                module,
+                pattern,
+                bindings,
+                cfg,
+            });
+            continue;
+        }
         let field_type = match &variant.fields {
             Fields::Unit => panic!("Every variant must have at least one unnamed field."),
             Fields::Named(_) => panic!("Can't have variant with named fields."),
@@ -99,99 +1659,2072 @@ fn parse_enum_definition(input: &DeriveInput) -> EnumData {
         variants.push((variant.ident.clone(), field_type));
     }
 
-    (input.ident.clone(), variants)
+    ((input.ident.clone(), variants), with)
 }
 
-fn gen_from_impls(enum_data: &EnumData) -> TokenStream {
-    let (enum_ident, variants) = enum_data;
-
-    let impls = variants.iter().map(|(ident, type_)| {
-        quote! {
-            impl From<#type_> for #enum_ident {
-                fn from(a: #type_) -> #enum_ident {
-                    Self::#ident(a)
-                }
+/// Returns the name and the field type of `input` if it is a newtype struct marked
+/// with `#[try_as(transparent)]`.
+fn parse_transparent(input: &DeriveInput) -> Option<(Ident, Type)> {
+    if !attr_flag(&input.attrs, "try_as", "transparent") {
+        return None;
+    }
+    if !input.generics.params.is_empty() {
+        panic!("Generic parameters aren't supported.");
+    }
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Some((input.ident.clone(), fields.unnamed[0].ty.clone()))
             }
-        }
-    });
+            _ => panic!("`#[try_as(transparent)]` requires a tuple struct with exactly one field."),
+        },
+        _ => panic!("`#[try_as(transparent)]` can only be used on structs."),
+    }
+}
 
-    TokenStream::from(quote! { #(#impls)* })
+/// Returns a catch-all arm for a `match` on a reference to an enum without variants,
+/// which rustc doesn't accept as exhaustive without one, and nothing otherwise.
+fn empty_enum_arm(variants: &[(Ident, Type)]) -> proc_macro2::TokenStream {
+    if variants.is_empty() {
+        quote! { _ => unreachable!() }
+    } else {
+        quote! {}
+    }
 }
 
-fn gen_try_into_impl(enum_data: &EnumData) -> TokenStream {
-    let (enum_ident, variants) = enum_data;
-    let impls = variants.iter().map(|(ident, type_)| {
-        quote! {
-            impl TryInto<#type_> for #enum_ident {
-                type Error = Self;
-                fn try_into(self) -> Result<#type_, Self::Error> {
-                    if let Self::#ident(a) = self {
-                        Ok(a)
-                    } else {
-                        Err(self)
-                    }
+/// Returns the attributes of each variant of the enum in `input`, in declaration order.
+fn variant_attrs(input: &DeriveInput) -> Vec<&[syn::Attribute]> {
+    match &input.data {
+        Data::Enum(data) => data.variants.iter().map(|v| &v.attrs[..]).collect(),
+        _ => panic!("Can only be derived from enums."),
+    }
+}
+
+/// Collects the arguments of all attributes `#[name(...)]` among `attrs`,
+/// e.g. of all `#[try_as(...)]` attributes.
+fn attr_args(attrs: &[syn::Attribute], name: &str) -> Vec<syn::Meta> {
+    use syn::{Meta, NestedMeta};
+
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(name))
+        .flat_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => list.nested.into_iter().map(|nested| match nested {
+                NestedMeta::Meta(meta) => meta,
+                NestedMeta::Lit(_) => {
+                    panic!("Expected arguments of the form `#[{}(key = value)]`.", name)
                 }
-            }
-        }
-    });
+            }),
+            _ => panic!("Expected an attribute of the form `#[{}(...)]`.", name),
+        })
+        .collect()
+}
 
-    TokenStream::from(quote! { #(#impls)* })
+/// Returns the value of the argument `key = "..."` of the attributes `#[name(...)]` among `attrs`.
+fn attr_str(attrs: &[syn::Attribute], name: &str, key: &str) -> Option<String> {
+    use syn::{Lit, Meta};
+
+    attr_args(attrs, name)
+        .into_iter()
+        .find_map(|meta| match meta {
+            Meta::NameValue(nv) if nv.path.is_ident(key) => match nv.lit {
+                Lit::Str(s) => Some(s.value()),
+                _ => panic!("Expected a string literal for `{}`.", key),
+            },
+            _ => None,
+        })
 }
 
-fn gen_try_as_ref(enum_data: &EnumData) -> TokenStream {
-    let (enum_ident, variants) = enum_data;
+/// Returns the value of the argument `key = ...` of the attributes `#[name(...)]` among
+/// `attrs`, which must be an integer literal.
+fn attr_int<N>(attrs: &[syn::Attribute], name: &str, key: &str) -> Option<N>
+where
+    N: std::str::FromStr,
+    N::Err: std::fmt::Display,
+{
+    use syn::{Lit, Meta};
 
-    let impls = variants.iter().map(|(ident, type_)| {
-        quote! {
-            impl try_as_traits::TryAsRef<#type_> for #enum_ident {
-                fn try_as_ref(&self) -> Option<&#type_>{
-                    if let Self::#ident(a) = self {
-                        Some(a)
-                    } else {
-                        None
-                    }
-                }
-            }
-        }
-    });
+    attr_args(attrs, name)
+        .into_iter()
+        .find_map(|meta| match meta {
+            Meta::NameValue(nv) if nv.path.is_ident(key) => match nv.lit {
+                Lit::Int(int) => Some(
+                    int.base10_parse()
+                        .unwrap_or_else(|e| panic!("Invalid value for `{}`: {}.", key, e)),
+                ),
+                _ => panic!("Expected an integer literal for `{}`.", key),
+            },
+            _ => None,
+        })
+}
 
-    TokenStream::from(quote! { #(#impls)* })
+/// Returns the tag of each variant, given by `#[try_as(tag = ...)]`.
+fn tags(input: &DeriveInput) -> Vec<u16> {
+    let tags: Vec<u16> = variant_attrs(input)
+        .into_iter()
+        .map(|attrs| {
+            attr_int(attrs, "try_as", "tag")
+                .unwrap_or_else(|| panic!("Mark every variant with `#[try_as(tag = ...)]`."))
+        })
+        .collect();
+    if tags.iter().collect::<HashSet<_>>().len() != tags.len() {
+        panic!("Each variant must have a different tag.");
+    }
+    tags
 }
 
-fn gen_try_as_mut(enum_data: &EnumData) -> TokenStream {
-    let (enum_ident, variants) = enum_data;
+/// Returns `true` exactly if one of the attributes `#[name(...)]` among `attrs`
+/// has the argument `key`, e.g. `#[serde(untagged)]`.
+fn attr_flag(attrs: &[syn::Attribute], name: &str, key: &str) -> bool {
+    attr_args(attrs, name)
+        .iter()
+        .any(|meta| matches!(meta, syn::Meta::Path(path) if path.is_ident(key)))
+}
 
-    let impls = variants.iter().map(|(ident, type_)| {
-        quote! {
-            impl TryAsMut<#type_> for #enum_ident {
-                fn try_as_mut(&mut self) -> Option<&mut #type_>{
-                    if let Self::#ident(a) = self {
-                        Some(a)
-                    } else {
-                        None
+/// Returns, for each variant of the enum in `input`, the attribute `#[cfg(...)]` given
+/// by `#[try_as(cfg(...))]` on the variant, or nothing.
+fn variant_cfgs(input: &DeriveInput) -> Vec<proc_macro2::TokenStream> {
+    use syn::Meta;
+
+    variant_attrs(input)
+        .into_iter()
+        .map(|attrs| {
+            attr_args(attrs, "try_as")
+                .into_iter()
+                .find_map(|meta| match meta {
+                    Meta::List(list) if list.path.is_ident("cfg") => {
+                        let predicate = list.nested;
+                        Some(quote! { #[cfg(#predicate)] })
                     }
-                }
+                    _ => None,
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Returns the integer type of the `#[repr(...)]` of the enum in `input`, if any.
+fn repr_int(input: &DeriveInput) -> Option<Ident> {
+    let ints = [
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+    ];
+    attr_args(&input.attrs, "repr")
+        .into_iter()
+        .find_map(|meta| match meta {
+            syn::Meta::Path(path) => path
+                .get_ident()
+                .filter(|i| ints.iter().any(|int| *i == int))
+                .cloned(),
+            _ => None,
+        })
+}
+
+/// Returns the discriminant of each variant of the enum in `input`, as an expression.
+/// Variants without an explicit discriminant follow the previous one, like in Rust.
+fn discriminants(input: &DeriveInput) -> Vec<proc_macro2::TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => panic!("Can only be derived from enums."),
+    };
+    let mut explicit = None;
+    let mut offset = 0;
+    data.variants
+        .iter()
+        .enumerate()
+        .map(|(i, variant)| {
+            if let Some((_, expr)) = &variant.discriminant {
+                explicit = Some(expr);
+                offset = 0;
             }
+            let discriminant = match explicit {
+                None => {
+                    let i = proc_macro2::Literal::usize_unsuffixed(i);
+                    quote! { #i }
+                }
+                Some(expr) if offset == 0 => quote! { #expr },
+                Some(expr) => {
+                    let offset = proc_macro2::Literal::usize_unsuffixed(offset);
+                    quote! { (#expr) + #offset }
+                }
+            };
+            offset += 1;
+            discriminant
+        })
+        .collect()
+}
+
+/// Returns `#[must_use]`, unless the enum in `input` is marked with `#[try_as(no_must_use)]`.
+fn must_use(input: &DeriveInput) -> proc_macro2::TokenStream {
+    if attr_flag(&input.attrs, "try_as", "no_must_use") {
+        quote! {}
+    } else {
+        quote! { #[must_use] }
+    }
+}
+
+/// Returns `true` exactly if the enum in `input` is marked with `#[non_exhaustive]`.
+fn is_non_exhaustive(input: &DeriveInput) -> bool {
+    input
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("non_exhaustive"))
+}
+
+/// Returns `true` exactly if the enum in `input` is marked with `#[try_as(compact)]`.
+fn is_compact(input: &DeriveInput) -> bool {
+    attr_flag(&input.attrs, "try_as", "compact")
+}
+
+/// Returns `output`, the code generated by the derive `derive` for `input`. If `input`
+/// is marked with `#[try_as(debug_expand)]`, the code is also returned as a string, in
+/// a hidden associated constant like `TRY_AS_EXPANDED_TRY_AS_REF` for `TryAsRef`.
+fn debug_expand(input: &DeriveInput, derive: &str, output: TokenStream) -> TokenStream {
+    if !attr_flag(&input.attrs, "try_as", "debug_expand") {
+        return output;
+    }
+    let ident = &input.ident;
+    let derive = snake_case(&Ident::new(derive, Span::call_site())).to_uppercase();
+    let constant = Ident::new(&format!("TRY_AS_EXPANDED_{}", derive), Span::call_site());
+    let output = proc_macro2::TokenStream::from(output);
+    let expanded = output.to_string();
+
+    TokenStream::from(quote! {
+        #output
+
+        impl #ident {
+            #[doc(hidden)]
+            pub const #constant: &'static str = #expanded;
         }
-    });
+    })
+}
 
-    TokenStream::from(quote! { #(#impls)* })
+/// A type generated alongside an enum, see [`companion`].
+struct Companion {
+    ident: Ident,
+    vis: syn::Visibility,
+    module: Option<Ident>,
+    derives: Vec<syn::Path>,
 }
 
-fn gen_typed_value(enum_data: &EnumData) -> TokenStream {
-    let (enum_ident, variants) = enum_data;
+/// The key of the derive generating the module shared by companion types, given by
+/// `#[try_as(module = "...")]`. Derives can't see each other, so only one of them may
+/// define it.
+const SHARED_MODULE_OWNER: &str = "columns";
 
-    let type_id_match_arms = variants.iter().map(|(ident, type_)| {
-        quote! {
-            #enum_ident::#ident(_) => std::any::TypeId::of::<#type_>()
+/// Returns the identifier, visibility and module of a type generated alongside the
+/// enum in `input`. They default to the name of the enum followed by `suffix`, the
+/// visibility of the enum and its module, and can be overridden with
+/// `#[try_as(key_name = "...", key_vis = "...", key_module = "...")]`. The derive
+/// of [`SHARED_MODULE_OWNER`] also falls back to `#[try_as(module = "...")]`, the
+/// others panic if it is given without `key_module`. Additional derives are listed
+/// with `#[try_as(key_derive(...))]`.
+fn companion(input: &DeriveInput, key: &str, suffix: &str) -> Companion {
+    let name = attr_str(&input.attrs, "try_as", &format!("{}_name", key))
+        .unwrap_or_else(|| format!("{}{}", input.ident, suffix));
+    let shared = attr_str(&input.attrs, "try_as", "module");
+    let module = match attr_str(&input.attrs, "try_as", &format!("{}_module", key)) {
+        Some(module) => Some(module),
+        None if key == SHARED_MODULE_OWNER => shared,
+        None => match shared {
+            Some(shared) => panic!(
+                "The module `{}` is generated by `Columns`, give this derive a module of its own with `{}_module`.",
+                shared, key
+            ),
+            None => None,
+        },
+    }
+    .map(|module| Ident::new(&module, Span::call_site()));
+    let vis = match attr_str(&input.attrs, "try_as", &format!("{}_vis", key)) {
+        Some(vis) => syn::parse_str(&vis)
+            .unwrap_or_else(|_| panic!("Expected a visibility for `{}_vis`.", key)),
+        // Keep private types visible next to the enum.
+        None if module.is_some() && matches!(input.vis, syn::Visibility::Inherited) => {
+            syn::parse_quote!(pub(super))
         }
-    });
+        None => input.vis.clone(),
+    };
+    Companion {
+        ident: Ident::new(&name, Span::call_site()),
+        vis,
+        module,
+        derives: attr_paths(&input.attrs, &format!("{}_derive", key)),
+    }
+}
 
-    TokenStream::from(quote! {
-        impl TypedContainer for #enum_ident {
-            fn type_id(&self) -> std::any::TypeId {
+/// Wraps `items`, generated for `companion`, in the module chosen for it, if any.
+/// The module has the visibility `vis` of the enum.
+fn in_module(items: TokenStream, companion: &Companion, vis: &syn::Visibility) -> TokenStream {
+    match &companion.module {
+        Some(module) => {
+            let items = proc_macro2::TokenStream::from(items);
+            TokenStream::from(quote! {
+                #vis mod #module {
+                    use super::*;
+
+                    #items
+                }
+            })
+        }
+        None => items,
+    }
+}
+
+/// Returns the paths listed in the argument `key(...)` of the `#[try_as(...)]` attributes
+/// among `attrs`, e.g. `Display` and `Debug` in `#[try_as(dyn(Display, Debug))]`.
+fn attr_paths(attrs: &[syn::Attribute], key: &str) -> Vec<syn::Path> {
+    use syn::{Meta, NestedMeta};
+
+    attr_args(attrs, "try_as")
+        .into_iter()
+        .filter_map(|meta| match meta {
+            Meta::List(list) if list.path.is_ident(key) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .map(|nested| match nested {
+            NestedMeta::Meta(Meta::Path(path)) => path,
+            _ => panic!("Expected a list of paths in `#[try_as({}(...))]`.", key),
+        })
+        .collect()
+}
+
+/// Converts an identifier like `PartialEq` to snake case, like `partial_eq`.
+fn snake_case(ident: &Ident) -> String {
+    let mut snake = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// Converts an identifier to snake case like [`snake_case`], as an identifier usable
+/// as a field name. Keywords like `type` become raw identifiers, and those that can't
+/// be raw, like `super`, get a trailing underscore.
+fn snake_case_ident(ident: &Ident) -> Ident {
+    let snake = snake_case(ident);
+    match syn::parse_str::<Ident>(&snake) {
+        Ok(ident) => ident,
+        Err(_) if matches!(snake.as_str(), "self" | "super" | "crate") => {
+            Ident::new(&format!("{}_", snake), Span::call_site())
+        }
+        Err(_) => Ident::new_raw(&snake, Span::call_site()),
+    }
+}
+
+/// Returns the type arguments of `type_` if it is an instance of the generic type
+/// `name`, e.g. `[T]` for `Vec<T>` and `"Vec"`.
+fn generic_args<'a>(type_: &'a Type, name: &str) -> Option<Vec<&'a Type>> {
+    let segment = match type_ {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != name {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => Some(
+            args.args
+                .iter()
+                .filter_map(|arg| match arg {
+                    syn::GenericArgument::Type(type_) => Some(type_),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns `true` exactly if `type_` is the path `ident`, e.g. `Self` or the enum itself.
+fn is_ident(type_: &Type, ident: &str) -> bool {
+    matches!(type_, Type::Path(path) if path.qself.is_none() && path.path.is_ident(ident))
+}
+
+/// Returns `true` exactly if `type_` is `Self` or the enum `enum_ident`.
+fn is_self(type_: &Type, enum_ident: &Ident) -> bool {
+    is_ident(type_, "Self") || is_ident(type_, &enum_ident.to_string())
+}
+
+/// Returns `true` exactly if `type_` is a `Vec` of the enum `enum_ident`.
+fn is_list_of(type_: &Type, enum_ident: &Ident) -> bool {
+    matches!(generic_args(type_, "Vec").as_deref(), Some([item]) if is_self(item, enum_ident))
+}
+
+/// Returns `true` exactly if `type_` is a `HashMap` or `BTreeMap` from `String` to
+/// the enum `enum_ident`.
+fn is_map_of(type_: &Type, enum_ident: &Ident) -> bool {
+    ["HashMap", "BTreeMap"].iter().any(|name| {
+        matches!(generic_args(type_, name).as_deref(),
+            Some([key, value]) if is_ident(key, "String") && is_self(value, enum_ident))
+    })
+}
+
+/// Renders a type the way it was written in the enum definition,
+/// without the whitespace `quote` inserts between tokens.
+fn type_name(type_: &Type) -> String {
+    let spaced = quote!(#type_).to_string();
+    let mut name = String::with_capacity(spaced.len());
+    let mut chars = spaced.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            let prev_is_word = name.ends_with(|p: char| p.is_alphanumeric() || p == '_');
+            let next_is_word = chars
+                .peek()
+                .is_some_and(|n| n.is_alphanumeric() || *n == '_');
+            // Keep `fn() -> u32` and `dyn Any + Send` spaced like rustfmt does.
+            let around_operator = name.ends_with("->")
+                || name.ends_with('+')
+                || chars.peek().is_some_and(|n| *n == '-' || *n == '+');
+            if !(prev_is_word && next_is_word || around_operator) {
+                continue;
+            }
+        }
+        name.push(c);
+    }
+    name
+}
+
+fn gen_from_impls(
+    enum_data: &EnumData,
+    cfgs: &[proc_macro2::TokenStream],
+    validators: &[Option<syn::Path>],
+    with: &[WithVariant],
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let impls =
+        variants
+            .iter()
+            .zip(cfgs)
+            .zip(validators)
+            .map(|(((ident, type_), cfg), validator)| match validator {
+                Some(validator) => quote! {
+                    #cfg
+                    impl TryFrom<#type_> for #enum_ident {
+                        type Error = #type_;
+                        fn try_from(a: #type_) -> Result<#enum_ident, #type_> {
+                            if #validator(&a) {
+                                Ok(Self::#ident(a))
+                            } else {
+                                Err(a)
+                            }
+                        }
+                    }
+                },
+                None => quote! {
+                    #cfg
+                    impl From<#type_> for #enum_ident {
+                        fn from(a: #type_) -> #enum_ident {
+                            Self::#ident(a)
+                        }
+                    }
+                },
+            });
+
+    let with_impls = with.iter().map(|variant| {
+        let WithVariant { module, cfg, .. } = variant;
+        quote! {
+            #cfg
+            impl From<#module::Target> for #enum_ident {
+                fn from(a: #module::Target) -> #enum_ident {
+                    #module::from(a)
+                }
+            }
+        }
+    });
+
+    TokenStream::from(quote! { #(#impls)* #(#with_impls)* })
+}
+
+fn gen_transparent_from((ident, type_): &(Ident, Type)) -> TokenStream {
+    TokenStream::from(quote! {
+        impl From<#type_> for #ident {
+            fn from(a: #type_) -> #ident {
+                #ident(a)
+            }
+        }
+
+        impl From<#ident> for #type_ {
+            fn from(a: #ident) -> #type_ {
+                a.0
+            }
+        }
+    })
+}
+
+fn gen_transparent_as_ref((ident, type_): &(Ident, Type)) -> TokenStream {
+    TokenStream::from(quote! {
+        impl AsRef<#type_> for #ident {
+            fn as_ref(&self) -> &#type_ {
+                &self.0
+            }
+        }
+    })
+}
+
+fn gen_transparent_as_mut((ident, type_): &(Ident, Type)) -> TokenStream {
+    TokenStream::from(quote! {
+        impl AsMut<#type_> for #ident {
+            fn as_mut(&mut self) -> &mut #type_ {
+                &mut self.0
+            }
+        }
+    })
+}
+
+fn gen_try_into_impl(
+    enum_data: &EnumData,
+    cfgs: &[proc_macro2::TokenStream],
+    with: &[WithVariant],
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    if let ([(ident, type_)], []) = (&variants[..], with) {
+        let cfg = &cfgs[0];
+        return TokenStream::from(quote! {
+            #cfg
+            impl From<#enum_ident> for #type_ {
+                fn from(value: #enum_ident) -> Self {
+                    let #enum_ident::#ident(a) = value;
+                    a
+                }
+            }
+        });
+    }
+    let impls = variants.iter().zip(cfgs).map(|((ident, type_), cfg)| {
+        quote! {
+            #cfg
+            impl TryInto<#type_> for #enum_ident {
+                type Error = Self;
+                fn try_into(self) -> Result<#type_, Self::Error> {
+                    if let Self::#ident(a) = self {
+                        Ok(a)
+                    } else {
+                        Err(self)
+                    }
+                }
+            }
+        }
+    });
+
+    let with_impls = with.iter().map(|variant| {
+        let WithVariant {
+            ident,
+            module,
+            pattern,
+            bindings,
+            cfg,
+        } = variant;
+        quote! {
+            #cfg
+            impl TryInto<#module::Target> for #enum_ident {
+                type Error = Self;
+                fn try_into(self) -> Result<#module::Target, Self::Error> {
+                    match self {
+                        Self::#ident #pattern => Ok(#module::into(#(#bindings),*)),
+                        #[allow(unreachable_patterns)]
+                        _ => Err(self),
+                    }
+                }
+            }
+        }
+    });
+
+    TokenStream::from(quote! { #(#impls)* #(#with_impls)* })
+}
+
+fn gen_into_option_impls(enum_data: &EnumData, cfgs: &[proc_macro2::TokenStream]) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let impls = variants.iter().zip(cfgs).map(|((ident, type_), cfg)| {
+        quote! {
+            #cfg
+            impl From<#enum_ident> for Option<#type_> {
+                fn from(a: #enum_ident) -> Option<#type_> {
+                    if let #enum_ident::#ident(a) = a {
+                        Some(a)
+                    } else {
+                        None
+                    }
+                }
+            }
+        }
+    });
+
+    TokenStream::from(quote! { #(#impls)* })
+}
+
+fn gen_try_from_ref(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let name_match_arms: Vec<_> = variants
+        .iter()
+        .map(|(ident, _)| {
+            quote! {
+                #enum_ident::#ident(_) => stringify!(#ident)
+            }
+        })
+        .collect();
+    let impls = variants.iter().map(|(ident, type_)| {
+        quote! {
+            impl TryFrom<&#enum_ident> for #type_ {
+                type Error = try_as_traits::ConversionError;
+
+                fn try_from(value: &#enum_ident) -> Result<Self, Self::Error> {
+                    match value {
+                        #enum_ident::#ident(a) => Ok(Clone::clone(a)),
+                        #[allow(unreachable_patterns)]
+                        _ => Err(try_as_traits::ConversionError {
+                            expected: core::any::type_name::<#type_>(),
+                            found: match value {
+                                #(#name_match_arms),*
+                            },
+                            value: None,
+                        }),
+                    }
+                }
+            }
+        }
+    });
+
+    TokenStream::from(quote! { #(#impls)* })
+}
+
+fn gen_try_new(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let attempts = variants.iter().map(|(ident, type_)| {
+        quote! {
+            if let Some(a) = slot.downcast_mut::<Option<#type_>>() {
+                return Ok(#enum_ident::#ident(a.take().unwrap()));
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl #enum_ident {
+            /// Wraps `value` if the enum can hold values of its type, and hands it back otherwise.
+            pub fn try_new<T: 'static>(value: T) -> Result<Self, T> {
+                // Moving the value through an `Option` avoids boxing it to downcast it.
+                let mut value = Some(value);
+                let slot: &mut dyn core::any::Any = &mut value;
+                #(#attempts)*
+                Err(value.unwrap())
+            }
+        }
+    })
+}
+
+fn gen_default(enum_data: &EnumData, index: usize) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let (ident, type_) = &variants[index];
+
+    TokenStream::from(quote! {
+        impl Default for #enum_ident {
+            fn default() -> Self {
+                #enum_ident::#ident(<#type_ as Default>::default())
+            }
+        }
+    })
+}
+
+fn gen_hash(enum_data: &EnumData, hash_by: &str) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let match_arms = variants.iter().enumerate().map(|(i, (ident, type_))| {
+        let id = match hash_by {
+            "index" => {
+                let index = i as u32;
+                quote! { #index }
+            }
+            "name" => {
+                let name = ident.to_string();
+                quote! { #name }
+            }
+            "type_id" => quote! { core::any::TypeId::of::<#type_>() },
+            _ => panic!("Expected `index`, `name` or `type_id` for `hash_by`."),
+        };
+        quote! {
+            #enum_ident::#ident(a) => core::hash::Hash::hash(&(#id, a), state)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl core::hash::Hash for #enum_ident {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                match self {
+                    #(#match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_sample_of_each(
+    enum_data: &EnumData,
+    samples: Vec<Option<syn::Expr>>,
+    test_roundtrip: bool,
+    validated: &[bool],
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let values = variants
+        .iter()
+        .zip(samples)
+        .map(|((ident, type_), sample)| match sample {
+            Some(sample) => quote! { #enum_ident::#ident(#sample) },
+            None => quote! { #enum_ident::#ident(<#type_ as Default>::default()) },
+        });
+    let test = test_roundtrip.then(|| gen_roundtrip_test(enum_data, validated));
+
+    TokenStream::from(quote! {
+        impl #enum_ident {
+            /// Returns one value per variant, in declaration order.
+            pub fn sample_of_each() -> Vec<Self> {
+                vec![#(#values),*]
+            }
+        }
+
+        #test
+    })
+}
+
+/// Generates a test checking that the conversions of the enum agree on the type held by
+/// each value returned by `sample_of_each`. Variants marked in `validated` are
+/// converted back with `TryFrom`, which they derive instead of `From`.
+fn gen_roundtrip_test(enum_data: &EnumData, validated: &[bool]) -> proc_macro2::TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let test_ident = Ident::new(
+        &format!("try_as_roundtrip_{}", snake_case(enum_ident)),
+        Span::call_site(),
+    );
+    let checks = variants
+        .iter()
+        .zip(validated)
+        .map(|((ident, type_), validated)| {
+            let variant = format!("{}::{}", enum_ident, ident);
+            let agreements = variants.iter().map(|(other, other_type)| {
+                let holds = other == ident;
+                let other_name = type_name(other_type);
+                quote! {
+                    assert_eq!(
+                        try_as_traits::TypedContainer::holds::<#other_type>(&value),
+                        #holds,
+                        "`TypedContainer::holds::<{}>` is wrong for `{}`", #other_name, #variant,
+                    );
+                    assert_eq!(
+                        try_as_traits::TryAsRef::<#other_type>::try_as_ref(&value).is_some(),
+                        #holds,
+                        "`TryAsRef<{}>` is wrong for `{}`", #other_name, #variant,
+                    );
+                }
+            });
+            let from = if *validated {
+                quote! {
+                    match TryFrom::<#type_>::try_from(payload) {
+                        Ok(value) => value,
+                        Err(_) => panic!("`TryFrom` rejects the sample of `{}`", #variant),
+                    }
+                }
+            } else {
+                quote! { #enum_ident::from(payload) }
+            };
+            quote! {
+                let value = samples.next().expect("one sample per variant");
+                #(#agreements)*
+                let payload: #type_ = match TryInto::<#type_>::try_into(value) {
+                    Ok(payload) => payload,
+                    Err(_) => panic!("`TryInto` fails for `{}`", #variant),
+                };
+                let value = #from;
+                assert!(
+                    matches!(value, #enum_ident::#ident(_)),
+                    "`From` doesn't convert back into `{}`", #variant,
+                );
+            }
+        });
+
+    quote! {
+        #[cfg(test)]
+        #[test]
+        fn #test_ident() {
+            let mut samples = #enum_ident::sample_of_each().into_iter();
+            #(#checks)*
+        }
+    }
+}
+
+fn gen_try_as_ref(
+    enum_data: &EnumData,
+    compact: bool,
+    cfgs: &[proc_macro2::TokenStream],
+    with: &[WithVariant],
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    if compact && !with.is_empty() {
+        panic!("`#[try_as(compact)]` can't be combined with `#[try_as(with = ...)]`.");
+    }
+
+    let impls: Vec<_> = if compact {
+        vec![quote! {
+            impl<T: 'static> try_as_traits::TryAsRef<T> for #enum_ident {
+                fn try_as_ref(&self) -> Option<&T> {
+                    try_as_traits::PayloadAny::payload_any(self).downcast_ref()
+                }
+            }
+        }]
+    } else {
+        variants
+            .iter()
+            .zip(cfgs)
+            .map(|((ident, type_), cfg)| {
+                quote! {
+                    #cfg
+                    impl try_as_traits::TryAsRef<#type_> for #enum_ident {
+                        fn try_as_ref(&self) -> Option<&#type_>{
+                            if let Self::#ident(a) = self {
+                                Some(a)
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                }
+            })
+            .collect()
+    };
+    let as_ref = variants
+        .iter()
+        .zip(cfgs)
+        .filter(|_| variants.len() == 1 && with.is_empty())
+        .map(|((ident, type_), cfg)| {
+            quote! {
+                #cfg
+                impl AsRef<#type_> for #enum_ident {
+                    fn as_ref(&self) -> &#type_ {
+                        let Self::#ident(a) = self;
+                        a
+                    }
+                }
+            }
+        });
+
+    let with_impls = with.iter().map(|variant| {
+        let WithVariant {
+            ident,
+            module,
+            pattern,
+            bindings,
+            cfg,
+        } = variant;
+        quote! {
+            #cfg
+            impl try_as_traits::TryAsRef<#module::Target> for #enum_ident {
+                fn try_as_ref(&self) -> Option<&#module::Target> {
+                    match self {
+                        Self::#ident #pattern => Some(#module::as_ref(#(#bindings),*)),
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                }
+            }
+        }
+    });
+
+    TokenStream::from(quote! { #(#impls)* #(#as_ref)* #(#with_impls)* })
+}
+
+fn gen_try_as_mut(
+    enum_data: &EnumData,
+    compact: bool,
+    cfgs: &[proc_macro2::TokenStream],
+    with: &[WithVariant],
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    if compact && !with.is_empty() {
+        panic!("`#[try_as(compact)]` can't be combined with `#[try_as(with = ...)]`.");
+    }
+
+    let impls: Vec<_> = if compact {
+        vec![quote! {
+            impl<T: 'static> TryAsMut<T> for #enum_ident {
+                fn try_as_mut(&mut self) -> Option<&mut T> {
+                    try_as_traits::PayloadAny::payload_any_mut(self).downcast_mut()
+                }
+            }
+        }]
+    } else {
+        variants
+            .iter()
+            .zip(cfgs)
+            .map(|((ident, type_), cfg)| {
+                quote! {
+                    #cfg
+                    impl TryAsMut<#type_> for #enum_ident {
+                        fn try_as_mut(&mut self) -> Option<&mut #type_>{
+                            if let Self::#ident(a) = self {
+                                Some(a)
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                }
+            })
+            .collect()
+    };
+    let as_mut = variants
+        .iter()
+        .zip(cfgs)
+        .filter(|_| variants.len() == 1 && with.is_empty())
+        .map(|((ident, type_), cfg)| {
+            quote! {
+                #cfg
+                impl AsMut<#type_> for #enum_ident {
+                    fn as_mut(&mut self) -> &mut #type_ {
+                        let Self::#ident(a) = self;
+                        a
+                    }
+                }
+            }
+        });
+
+    let with_impls = with.iter().map(|variant| {
+        let WithVariant {
+            ident,
+            module,
+            pattern,
+            bindings,
+            cfg,
+        } = variant;
+        quote! {
+            #cfg
+            impl TryAsMut<#module::Target> for #enum_ident {
+                fn try_as_mut(&mut self) -> Option<&mut #module::Target> {
+                    match self {
+                        Self::#ident #pattern => Some(#module::as_mut(#(#bindings),*)),
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                }
+            }
+        }
+    });
+
+    TokenStream::from(quote! { #(#impls)* #(#as_mut)* #(#with_impls)* })
+}
+
+fn gen_typed_value(enum_data: &EnumData, compact: bool, with: &[WithVariant]) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = if with.is_empty() {
+        empty_enum_arm(variants)
+    } else {
+        quote! {}
+    };
+
+    // The variants marked with `#[try_as(with = ...)]`, with the type they hold.
+    let with: Vec<_> = with
+        .iter()
+        .map(|variant| {
+            let WithVariant { ident, module, .. } = variant;
+            (
+                quote! { #enum_ident::#ident { .. } },
+                quote! { #module::Target },
+            )
+        })
+        .collect();
+    let arms = variants
+        .iter()
+        .map(|(ident, type_)| (quote! { #enum_ident::#ident(_) }, quote! { #type_ }))
+        .chain(with);
+
+    let type_id_match_arms = arms.clone().map(|(pattern, type_)| {
+        quote! {
+            #pattern => core::any::TypeId::of::<#type_>()
+        }
+    });
+    let type_id = if compact {
+        quote! {
+            core::any::Any::type_id(try_as_traits::PayloadAny::payload_any(self))
+        }
+    } else {
+        quote! {
+            match self {
+                #(#type_id_match_arms,)*
+                #empty
+            }
+        }
+    };
+
+    let type_name_match_arms = arms.map(|(pattern, type_)| {
+        quote! {
+            #pattern => core::any::type_name::<#type_>()
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl TypedContainer for #enum_ident {
+            fn type_id(&self) -> core::any::TypeId {
+                #type_id
+            }
+
+            fn type_name(&self) -> &'static str {
+                match self {
+                    #(#type_name_match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_typed_container_mut(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let attempts = variants.iter().map(|(ident, type_)| {
+        quote! {
+            let value = match value.downcast::<#type_>() {
+                Ok(a) => return Ok(into_any(core::mem::replace(self, #enum_ident::#ident(*a)))),
+                Err(value) => value,
+            };
+        }
+    });
+    let into_any_match_arms = variants.iter().map(|(ident, _)| {
+        quote! {
+            #enum_ident::#ident(a) => Box::new(a)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::TypedContainerMut for #enum_ident {
+            fn replace_any(
+                &mut self,
+                value: Box<dyn core::any::Any>,
+            ) -> Result<Box<dyn core::any::Any>, Box<dyn core::any::Any>> {
+                let into_any = |old: Self| -> Box<dyn core::any::Any> {
+                    match old {
+                        #(#into_any_match_arms),*
+                    }
+                };
+                #(#attempts)*
+                Err(value)
+            }
+        }
+    })
+}
+
+fn gen_payload_any(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+    let idents = variants.iter().map(|(ident, _)| ident).collect::<Vec<_>>();
+
+    TokenStream::from(quote! {
+        impl try_as_traits::PayloadAny for #enum_ident {
+            fn payload_any(&self) -> &dyn core::any::Any {
+                match self {
+                    #(#enum_ident::#idents(a) => a,)*
+                    #empty
+                }
+            }
+
+            fn payload_any_mut(&mut self) -> &mut dyn core::any::Any {
+                match self {
+                    #(#enum_ident::#idents(a) => a,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_type_names(enum_data: &EnumData, tags: Option<&[u16]>) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let type_names = variants.iter().map(|(_, type_)| type_name(type_));
+    let variant_names = variants.iter().map(|(ident, _)| ident.to_string());
+    let fingerprint = tags.map(|tags| {
+        quote! {
+            const TYPESET_FINGERPRINT: u64 =
+                try_as_traits::__private::fingerprint_tagged(Self::TYPE_NAMES, &[#(#tags),*]);
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::TypeNames for #enum_ident {
+            const TYPE_NAMES: &'static [&'static str] = &[#(#type_names),*];
+            const VARIANT_NAMES: &'static [&'static str] = &[#(#variant_names),*];
+            #fingerprint
+        }
+    })
+}
+
+fn gen_type_set(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let types: Vec<_> = variants.iter().map(|(_, type_)| type_).collect();
+
+    TokenStream::from(quote! {
+        impl try_as_traits::TypeSet for #enum_ident {
+            type Types = (#(#types,)*);
+        }
+
+        #(impl try_as_traits::ContainsType<#types> for #enum_ident {})*
+
+        impl #enum_ident {
+            #[doc(hidden)]
+            pub fn __assert_types_contained_in<O>()
+            where
+                #(O: try_as_traits::ContainsType<#types>,)*
+            {
+            }
+        }
+    })
+}
+
+fn gen_eq_by_inner(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let match_arms = variants.iter().map(|(ident, type_)| {
+        quote! {
+            #enum_ident::#ident(a) => other
+                .payload_any()
+                .downcast_ref::<#type_>()
+                .is_some_and(|b| a == b)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl<O: try_as_traits::PayloadAny + ?Sized> try_as_traits::EqByInner<O> for #enum_ident {
+            fn eq_inner(&self, other: &O) -> bool {
+                match self {
+                    #(#match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_discriminant(
+    enum_data: &EnumData,
+    repr: &Ident,
+    discriminants: &[proc_macro2::TokenStream],
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let match_arms = variants.iter().enumerate().map(|(i, (ident, _))| {
+        quote! {
+            #enum_ident::#ident(_) => Self::DISCRIMINANTS[#i]
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl #enum_ident {
+            /// The discriminant of each variant, in declaration order.
+            pub const DISCRIMINANTS: &'static [#repr] = &[#(#discriminants),*];
+
+            /// Returns the discriminant of the variant of `self`, as declared.
+            pub const fn discriminant(&self) -> #repr {
+                match self {
+                    #(#match_arms),*
+                }
+            }
+        }
+    })
+}
+
+fn gen_introspect(enum_data: &EnumData, non_exhaustive: bool, type_index: bool) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let infos = variants.iter().map(|(ident, type_)| {
+        let name = ident.to_string();
+        let type_name = type_name(type_);
+        quote! {
+            try_as_traits::VariantInfo {
+                name: #name,
+                type_name: #type_name,
+                type_id: core::any::TypeId::of::<#type_>(),
+                size: core::mem::size_of::<#type_>(),
+                align: core::mem::align_of::<#type_>(),
+            }
+        }
+    });
+    let index_match_arms = variants.iter().enumerate().map(|(i, (ident, _))| {
+        quote! {
+            #enum_ident::#ident(_) => #i
+        }
+    });
+    let non_exhaustive = non_exhaustive.then(|| quote! { const NON_EXHAUSTIVE: bool = true; });
+    let index_of_type_id = type_index.then(|| {
+        quote! {
+            fn index_of_type_id(type_id: core::any::TypeId) -> Option<usize> {
+                static INDEX: std::sync::OnceLock<try_as_traits::TypeIndex> = std::sync::OnceLock::new();
+                INDEX
+                    .get_or_init(|| {
+                        try_as_traits::TypeIndex::new(Self::TYPES.iter().map(|info| info.type_id))
+                    })
+                    .get(type_id)
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::Introspect for #enum_ident {
+            const TYPES: &'static [try_as_traits::VariantInfo] = &[#(#infos),*];
+            #non_exhaustive
+            #index_of_type_id
+
+            fn variant_index(&self) -> usize {
+                match self {
+                    #(#index_match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_instantiate(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let match_arms = variants.iter().enumerate().map(|(i, (ident, type_))| {
+        quote! {
+            #i => (&DefaultIfAvailable::<#type_>(core::marker::PhantomData))
+                .make()
+                .map(#enum_ident::#ident)
+                .ok_or(try_as_traits::InstantiateError::NoDefault { index })
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::Instantiate for #enum_ident {
+            fn instantiate(index: usize) -> Result<Self, try_as_traits::InstantiateError> {
+                #[allow(unused_imports)]
+                use try_as_traits::__private::{DefaultIfAvailable, MakeDefault, MakeNothing};
+                match index {
+                    #(#match_arms,)*
+                    _ => Err(try_as_traits::InstantiateError::InvalidIndex { index }),
+                }
+            }
+        }
+    })
+}
+
+fn gen_describe(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let match_arms = variants.iter().map(|(ident, type_)| {
+        let variant = ident.to_string();
+        let type_name = type_name(type_);
+        quote! {
+            #enum_ident::#ident(a) => try_as_traits::ValueDescription {
+                variant: #variant,
+                type_name: #type_name,
+                value: (&try_as_traits::__private::DebugIfAvailable::<#type_>(a)).render(),
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::Describe for #enum_ident {
+            fn describe(&self) -> try_as_traits::ValueDescription {
+                #[allow(unused_imports)]
+                use try_as_traits::__private::{RenderDebug, RenderNothing};
+                match self {
+                    #(#match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_variant_name(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let name_match_arms = variants.iter().map(|(ident, _)| {
+        quote! {
+            #enum_ident::#ident(_) => stringify!(#ident)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::VariantName for #enum_ident {
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    #(#name_match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_tagged(enum_data: &EnumData, tags: &[u16]) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let tag_match_arms = variants.iter().zip(tags).map(|((ident, _), tag)| {
+        quote! {
+            #enum_ident::#ident(_) => #tag
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::Tagged for #enum_ident {
+            const TAGS: &'static [u16] = &[#(#tags),*];
+
+            fn tag(&self) -> u16 {
+                match self {
+                    #(#tag_match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_wire_encode(enum_data: &EnumData, tags: &[u16]) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let match_arms = variants.iter().zip(tags).map(|((ident, _), tag)| {
+        let tag = *tag as u64;
+        quote! {
+            #enum_ident::#ident(a) => {
+                try_as_traits::encoding::write_varint(out, #tag);
+                try_as_traits::encoding::WireEncode::wire_encode(a, out);
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::encoding::WireEncode for #enum_ident {
+            fn wire_encode(&self, out: &mut Vec<u8>) {
+                match self {
+                    #(#match_arms)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_wire_decode(enum_data: &EnumData, tags: &[u16]) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let match_arms = variants.iter().zip(tags).map(|((ident, type_), tag)| {
+        let tag = *tag as u64;
+        quote! {
+            #tag => Ok(#enum_ident::#ident(
+                <#type_ as try_as_traits::encoding::WireDecode>::wire_decode(input)?,
+            ))
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::encoding::WireDecode for #enum_ident {
+            fn wire_decode(input: &mut &[u8]) -> Result<Self, try_as_traits::encoding::DecodeError> {
+                match try_as_traits::encoding::read_varint(input)? {
+                    #(#match_arms,)*
+                    tag => Err(try_as_traits::encoding::DecodeError::InvalidTag(tag)),
+                }
+            }
+        }
+    })
+}
+
+fn gen_registrable(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let entries = variants.iter().map(|(ident, type_)| {
+        let name = type_name(type_);
+        quote! {
+            try_as_traits::RegistryEntry {
+                type_name: #name,
+                type_id: core::any::TypeId::of::<#type_>(),
+                construct: |value| value.downcast::<#type_>().map(|a| #enum_ident::#ident(*a)),
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::Registrable for #enum_ident {
+            fn registry_entries() -> Vec<try_as_traits::RegistryEntry<Self>> {
+                vec![#(#entries),*]
+            }
+        }
+    })
+}
+
+fn gen_constructors(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let entries = variants.iter().map(|(ident, type_)| {
+        quote! {
+            (
+                core::any::TypeId::of::<#type_>(),
+                |value| value.downcast::<#type_>().map(|a| #enum_ident::#ident(*a)),
+            )
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl #enum_ident {
+            /// The [`std::any::TypeId`] of each type the enum can hold, with a
+            /// function wrapping a type-erased value of that type into the enum.
+            pub const CONSTRUCTORS: &'static [(
+                core::any::TypeId,
+                try_as_traits::Constructor<#enum_ident>,
+            )] = &[#(#entries),*];
+        }
+    })
+}
+
+fn gen_try_from_any_ref(
+    enum_data: &EnumData,
+    must_use: &proc_macro2::TokenStream,
+    type_index: bool,
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let attempts = variants.iter().map(|(ident, type_)| {
+        quote! {
+            if let Some(a) = value.downcast_ref::<#type_>() {
+                return Some(#enum_ident::#ident(Clone::clone(a)));
+            }
+        }
+    });
+    let body = if type_index {
+        let types = variants.iter().map(|(_, type_)| type_);
+        let arms = variants.iter().enumerate().map(|(i, (ident, type_))| {
+            quote! {
+                Some(#i) => value.downcast_ref::<#type_>().map(|a| #enum_ident::#ident(Clone::clone(a)))
+            }
+        });
+        quote! {
+            static INDEX: std::sync::OnceLock<try_as_traits::TypeIndex> = std::sync::OnceLock::new();
+            let index = INDEX.get_or_init(|| {
+                try_as_traits::TypeIndex::new([#(core::any::TypeId::of::<#types>()),*])
+            });
+            match index.get(core::any::Any::type_id(value)) {
+                #(#arms,)*
+                _ => None,
+            }
+        }
+    } else {
+        quote! {
+            #(#attempts)*
+            None
+        }
+    };
+
+    TokenStream::from(quote! {
+        impl #enum_ident {
+            /// Builds the enum from a clone of `value`, if the enum can hold its type.
+            #must_use
+            pub fn try_from_any_ref(value: &dyn core::any::Any) -> Option<Self> {
+                #body
+            }
+        }
+    })
+}
+
+fn gen_into_boxed_any(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let match_arms = variants.iter().map(|(ident, _)| {
+        quote! {
+            #enum_ident::#ident(a) => Box::new(a)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl From<#enum_ident> for Box<dyn core::any::Any + Send + Sync> {
+            fn from(a: #enum_ident) -> Self {
+                match a {
+                    #(#match_arms),*
+                }
+            }
+        }
+    })
+}
+
+fn gen_into_boxed_error(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let match_arms = variants.iter().map(|(ident, _)| {
+        quote! {
+            #enum_ident::#ident(a) => Box::new(a)
+        }
+    });
+    let attempts = variants.iter().map(|(ident, type_)| {
+        quote! {
+            let error = match error.downcast::<#type_>() {
+                Ok(a) => return Ok(#enum_ident::#ident(*a)),
+                Err(error) => error,
+            };
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl From<#enum_ident> for Box<dyn std::error::Error + Send + Sync> {
+            fn from(a: #enum_ident) -> Self {
+                match a {
+                    #(#match_arms),*
+                }
+            }
+        }
+
+        impl #enum_ident {
+            /// Converts a boxed error back into the enum, if the enum can hold its type.
+            /// Otherwise, hands back the box.
+            pub fn try_downcast(
+                error: Box<dyn std::error::Error + Send + Sync>,
+            ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+                #(#attempts)*
+                Err(error)
+            }
+        }
+    })
+}
+
+fn gen_error_enum(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let fmt_match_arms = variants.iter().map(|(ident, _)| {
+        quote! {
+            #enum_ident::#ident(a) => std::fmt::Display::fmt(a, f)
+        }
+    });
+    let source_match_arms = variants.iter().map(|(ident, _)| {
+        quote! {
+            #enum_ident::#ident(a) => std::error::Error::source(a)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl std::fmt::Display for #enum_ident {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(#fmt_match_arms,)*
+                    #empty
+                }
+            }
+        }
+
+        impl std::error::Error for #enum_ident {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                match self {
+                    #(#source_match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_as_dyn(
+    enum_data: &EnumData,
+    traits: &[syn::Path],
+    into_traits: &[syn::Path],
+    must_use: &proc_macro2::TokenStream,
+) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let methods = traits.iter().map(|trait_| {
+        let trait_name = snake_case(&trait_.segments.last().unwrap().ident);
+        let as_dyn = Ident::new(&format!("as_dyn_{}", trait_name), Span::call_site());
+        let as_dyn_mut = Ident::new(&format!("as_dyn_{}_mut", trait_name), Span::call_site());
+        let match_arms: Vec<_> = variants
+            .iter()
+            .map(|(ident, _)| quote! { #enum_ident::#ident(a) => a })
+            .collect();
+        quote! {
+            /// Returns the contained value as a trait object.
+            pub fn #as_dyn(&self) -> &dyn #trait_ {
+                match self {
+                    #(#match_arms,)*
+                    #empty
+                }
+            }
+
+            /// Returns the contained value as a mutable trait object.
+            pub fn #as_dyn_mut(&mut self) -> &mut dyn #trait_ {
+                match self {
+                    #(#match_arms,)*
+                    #empty
+                }
+            }
+        }
+    });
+
+    let into_methods = into_traits.iter().map(|trait_| {
+        let trait_name = snake_case(&trait_.segments.last().unwrap().ident);
+        let into_dyn = Ident::new(&format!("into_dyn_{}", trait_name), Span::call_site());
+        let match_arms = variants
+            .iter()
+            .map(|(ident, _)| quote! { #enum_ident::#ident(a) => Box::new(a) });
+        quote! {
+            /// Converts the contained value into a boxed trait object.
+            #must_use
+            pub fn #into_dyn(self) -> Box<dyn #trait_> {
+                match self {
+                    #(#match_arms,)*
+                    #empty
+                }
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl #enum_ident {
+            #(#methods)*
+            #(#into_methods)*
+        }
+    })
+}
+
+fn gen_columns(enum_data: &EnumData, columns: &Companion) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let Companion {
+        ident: columns_ident,
+        vis,
+        derives,
+        ..
+    } = columns;
+
+    let fields: Vec<_> = variants
+        .iter()
+        .map(|(ident, _)| Ident::new(&format!("{}_column", snake_case(ident)), Span::call_site()))
+        .collect();
+    let types: Vec<_> = variants.iter().map(|(_, type_)| type_).collect();
+    let insert_match_arms = variants.iter().zip(&fields).map(|((ident, _), field)| {
+        quote! {
+            #enum_ident::#ident(a) => self.#field.push(a)
+        }
+    });
+    let doc = format!(
+        "A storage for values of [`{}`], keeping the values of each type in a dense column.",
+        enum_ident
+    );
+
+    TokenStream::from(quote! {
+        #[doc = #doc]
+        #[derive(Default, #(#derives),*)]
+        #vis struct #columns_ident {
+            #(#fields: Vec<#types>),*
+        }
+
+        impl #columns_ident {
+            /// Creates an empty storage.
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// Adds a value to the column of its type.
+            pub fn insert(&mut self, value: impl Into<#enum_ident>) {
+                match value.into() {
+                    #(#insert_match_arms),*
+                }
+            }
+
+            /// Returns the number of values in all columns.
+            pub fn len(&self) -> usize {
+                0 #(+ self.#fields.len())*
+            }
+
+            /// Returns `true` exactly if all columns are empty.
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Iterates over the values of type `T`.
+            pub fn iter<T>(&self) -> std::slice::Iter<'_, T>
+            where
+                Self: try_as_traits::DenseColumn<T>,
+            {
+                try_as_traits::DenseColumn::column(self).iter()
+            }
+
+            /// Iterates mutably over the values of type `T`.
+            pub fn iter_mut<T>(&mut self) -> std::slice::IterMut<'_, T>
+            where
+                Self: try_as_traits::DenseColumn<T>,
+            {
+                try_as_traits::DenseColumn::column_mut(self).iter_mut()
+            }
+        }
+
+        #(
+            impl try_as_traits::DenseColumn<#types> for #columns_ident {
+                fn column(&self) -> &Vec<#types> {
+                    &self.#fields
+                }
+
+                fn column_mut(&mut self) -> &mut Vec<#types> {
+                    &mut self.#fields
+                }
+            }
+        )*
+    })
+}
+
+fn gen_ffi(enum_data: &EnumData, ffi_ident: &Ident, vis: &syn::Visibility) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    if variants.is_empty() {
+        panic!("`Ffi` can't be derived for enums without variants.");
+    }
+    if variants.len() > 256 {
+        panic!("The tag of `Ffi` can't distinguish more than 256 variants.");
+    }
+    let payload_ident = Ident::new(&format!("{}Payload", ffi_ident), Span::call_site());
+    let fields: Vec<_> = variants
+        .iter()
+        .map(|(ident, _)| snake_case_ident(ident))
+        .collect();
+    let types = variants.iter().map(|(_, type_)| type_);
+    let into_match_arms =
+        variants
+            .iter()
+            .zip(&fields)
+            .enumerate()
+            .map(|(i, ((ident, _), field))| {
+                let tag = i as u8;
+                quote! {
+                    #enum_ident::#ident(a) => #ffi_ident {
+                        tag: #tag,
+                        payload: #payload_ident { #field: a },
+                    }
+                }
+            });
+    let from_match_arms =
+        variants
+            .iter()
+            .zip(&fields)
+            .enumerate()
+            .map(|(i, ((ident, _), field))| {
+                let tag = i as u8;
+                // The tag names the variant, so the field holds a valid value, as
+                // guaranteed by the caller of `into_enum`.
+                quote! {
+                    #tag => Ok(#enum_ident::#ident(unsafe { self.payload.#field }))
+                }
+            });
+    let doc = format!(
+        "The values of [`{}`] as a `#[repr(C)]` tagged union.",
+        enum_ident
+    );
+    let payload_doc = format!("The payload of a [`{}`].", ffi_ident);
+
+    TokenStream::from(quote! {
+        #[doc = #doc]
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        #vis struct #ffi_ident {
+            /// The index of the variant, in declaration order.
+            pub tag: u8,
+            /// The contained value, in the field named after the variant.
+            pub payload: #payload_ident,
+        }
+
+        #[doc = #payload_doc]
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        #vis union #payload_ident {
+            #(pub #fields: #types),*
+        }
+
+        impl From<#enum_ident> for #ffi_ident {
+            fn from(value: #enum_ident) -> Self {
+                match value {
+                    #(#into_match_arms),*
+                }
+            }
+        }
+
+        impl #ffi_ident {
+            /// Converts the value back into the enum, failing if the tag doesn't name a variant.
+            ///
+            /// # Safety
+            ///
+            /// If the tag names a variant, the field of the payload named after the variant
+            /// must hold a valid value of its type, like in values created by `From`.
+            #vis unsafe fn into_enum(self) -> Result<#enum_ident, try_as_traits::InvalidTag> {
+                match self.tag {
+                    #(#from_match_arms,)*
+                    tag => Err(try_as_traits::InvalidTag { tag: tag as u64 }),
+                }
+            }
+        }
+    })
+}
+
+fn gen_index(enum_data: &EnumData, null: Option<usize>) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let (key_miss, index_miss) = match null {
+        Some(i) => {
+            let (ident, type_) = &variants[i];
+            if !matches!(type_, Type::Tuple(tuple) if tuple.elems.is_empty()) {
+                panic!("The variant marked with `#[try_as(null)]` must hold `()`.");
+            }
+            let miss = quote! {{
+                static NULL: #enum_ident = #enum_ident::#ident(());
+                &NULL
+            }};
+            (miss.clone(), miss)
+        }
+        None => (
+            quote! { panic!("no child with key `{}`", key) },
+            quote! { panic!("no child at index {}", index) },
+        ),
+    };
+
+    TokenStream::from(quote! {
+        impl std::ops::Index<&str> for #enum_ident {
+            type Output = Self;
+
+            fn index(&self, key: &str) -> &Self {
+                match try_as_traits::Navigate::child_key(self, key) {
+                    Some(child) => child,
+                    None => #key_miss,
+                }
+            }
+        }
+
+        impl std::ops::Index<usize> for #enum_ident {
+            type Output = Self;
+
+            fn index(&self, index: usize) -> &Self {
+                match try_as_traits::Navigate::child_index(self, index) {
+                    Some(child) => child,
+                    None => #index_miss,
+                }
+            }
+        }
+    })
+}
+
+fn gen_navigate(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let lists = variants
+        .iter()
+        .filter(|(_, type_)| is_list_of(type_, enum_ident));
+    let maps = variants
+        .iter()
+        .filter(|(_, type_)| is_map_of(type_, enum_ident));
+    let list_idents = lists.map(|(ident, _)| ident);
+    let map_idents = maps.map(|(ident, _)| ident);
+
+    TokenStream::from(quote! {
+        impl try_as_traits::Navigate for #enum_ident {
+            fn child_key(&self, key: &str) -> Option<&Self> {
+                match self {
+                    #(#enum_ident::#map_idents(a) => a.get(key),)*
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                }
+            }
+
+            fn child_index(&self, index: usize) -> Option<&Self> {
+                match self {
+                    #(#enum_ident::#list_idents(a) => a.get(index),)*
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                }
+            }
+        }
+    })
+}
+
+fn gen_diff(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let diff_match_arms = variants.iter().map(|(ident, type_)| {
+        let diff = if is_list_of(type_, enum_ident) {
+            quote! { try_as_traits::diff::diff_lists(a, b) }
+        } else if is_map_of(type_, enum_ident) {
+            quote! { try_as_traits::diff::diff_maps(a, b) }
+        } else {
+            quote! {
+                if a == b {
+                    try_as_traits::diff::ValueDiff::Same
+                } else {
+                    try_as_traits::diff::ValueDiff::ValueChanged
+                }
+            }
+        };
+        quote! {
+            (#enum_ident::#ident(a), #enum_ident::#ident(b)) => #diff
+        }
+    });
+    let name_match_arms = variants.iter().map(|(ident, _)| {
+        let name = ident.to_string();
+        quote! {
+            #enum_ident::#ident(_) => #name
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::diff::Diff for #enum_ident {
+            fn diff(&self, other: &Self) -> try_as_traits::diff::ValueDiff {
+                let name = |value: &Self| match value {
+                    #(#name_match_arms,)*
+                    #empty
+                };
+                #[allow(unreachable_patterns)]
+                match (self, other) {
+                    #(#diff_match_arms,)*
+                    _ => try_as_traits::diff::ValueDiff::TypeChanged {
+                        from: name(self),
+                        to: name(other),
+                    },
+                }
+            }
+        }
+    })
+}
+
+fn gen_merge(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let match_arms = variants.iter().filter_map(|(ident, type_)| {
+        let merge = if is_list_of(type_, enum_ident) {
+            quote! { try_as_traits::merge::merge_lists(a, b, strategy) }
+        } else if is_map_of(type_, enum_ident) {
+            quote! { try_as_traits::merge::merge_maps(a, b, strategy) }
+        } else {
+            return None;
+        };
+        Some(quote! {
+            (#enum_ident::#ident(a), #enum_ident::#ident(b)) => #enum_ident::#ident(#merge)
+        })
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::merge::Merge for #enum_ident {
+            fn merge(self, other: Self, strategy: try_as_traits::merge::MergeStrategy) -> Self {
+                match (self, other) {
+                    #(#match_arms,)*
+                    (_, other) => other,
+                }
+            }
+        }
+    })
+}
+
+fn gen_deep_size(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let match_arms = variants.iter().map(|(ident, _)| {
+        quote! {
+            #enum_ident::#ident(a) => try_as_traits::deep_size::DeepSize::heap_size(a)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::deep_size::DeepSize for #enum_ident {
+            fn heap_size(&self) -> usize {
+                match self {
+                    #(#match_arms,)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_pretty_print(enum_data: &EnumData) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+    let empty = empty_enum_arm(variants);
+
+    let match_arms = variants.iter().map(|(ident, type_)| {
+        let variant = ident.to_string();
+        let type_name = type_name(type_);
+        let write = if is_list_of(type_, enum_ident) {
+            quote! {
+                let children = a.iter().enumerate().map(|(i, child)| (format!("[{}]", i), child));
+                try_as_traits::pretty::write_children(out, #variant, #type_name, children, options, depth)
+            }
+        } else if is_map_of(type_, enum_ident) {
+            quote! {
+                let mut children: Vec<_> = a.iter().map(|(key, child)| (key, child)).collect();
+                children.sort_by(|x, y| x.0.cmp(y.0));
+                let children = children.into_iter().map(|(key, child)| (format!("{:?}", key), child));
+                try_as_traits::pretty::write_children(out, #variant, #type_name, children, options, depth)
+            }
+        } else {
+            quote! {
+                try_as_traits::pretty::write_leaf(out, #variant, #type_name, a, options)
+            }
+        };
+        quote! {
+            #enum_ident::#ident(a) => {
+                #write
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl try_as_traits::pretty::PrettyPrint for #enum_ident {
+            fn write_pretty(
+                &self,
+                out: &mut dyn std::fmt::Write,
+                options: &try_as_traits::pretty::PrettyOptions,
+                depth: usize,
+            ) -> std::fmt::Result {
+                match self {
+                    #(#match_arms)*
+                    #empty
+                }
+            }
+        }
+    })
+}
+
+fn gen_into_parts(enum_data: &EnumData, must_use: &proc_macro2::TokenStream) -> TokenStream {
+    let (enum_ident, variants) = enum_data;
+
+    let part_types = variants.iter().map(|(_, type_)| quote! { Option<#type_> });
+    let match_arms = variants.iter().enumerate().map(|(i, (ident, _))| {
+        let parts = (0..variants.len()).map(|j| {
+            if i == j {
+                quote! { Some(a) }
+            } else {
+                quote! { None }
+            }
+        });
+        quote! {
+            #enum_ident::#ident(a) => (#(#parts,)*)
+        }
+    });
+
+    TokenStream::from(quote! {
+        impl #enum_ident {
+            /// Converts the enum into a tuple with one `Option` per variant,
+            /// exactly one of which is `Some`.
+            #must_use
+            pub fn into_parts(self) -> (#(#part_types,)*) {
                 match self {
-                    #(#type_id_match_arms),*
+                    #(#match_arms),*
                 }
             }
         }
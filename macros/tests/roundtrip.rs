@@ -0,0 +1,49 @@
+//! Compiles and runs the tests generated by `#[try_as(test_roundtrip)]`.
+
+use std::num::NonZeroU8;
+
+use try_as_traits::TypedContainer;
+
+fn is_port(port: &u16) -> bool {
+    *port != 0
+}
+
+#[derive(
+    Debug,
+    PartialEq,
+    try_as_macros::SampleOfEach,
+    try_as_macros::From,
+    try_as_macros::TryInto,
+    try_as_macros::TryAsRef,
+    try_as_macros::TypedContainer,
+)]
+#[try_as(test_roundtrip)]
+enum Value {
+    Number(i64),
+    Text(String),
+    #[try_as(sample = "NonZeroU8::MIN")]
+    Count(NonZeroU8),
+    List(Vec<Value>),
+    #[try_as(validate = "is_port", sample = "8080")]
+    Port(u16),
+}
+
+#[test]
+fn generates_one_sample_per_variant() {
+    assert_eq!(
+        Value::sample_of_each(),
+        [
+            Value::Number(0),
+            Value::Text(String::new()),
+            Value::Count(NonZeroU8::MIN),
+            Value::List(Vec::new()),
+            Value::Port(8080),
+        ]
+    );
+}
+
+#[test]
+fn generates_the_roundtrip_test() {
+    // The generated test is a plain function, so it can be called by name as well.
+    try_as_roundtrip_value();
+}
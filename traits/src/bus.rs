@@ -0,0 +1,235 @@
+//! An asynchronous event bus for enums, routing values to subscriptions by the type
+//! they hold, see [`AsyncTypedBus`].
+//!
+//! The bus doesn't depend on an async runtime, subscriptions wake the task awaiting
+//! them through the [`Waker`] of its context, so they can be used with any executor.
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use crate::PayloadAny;
+
+/// The values sent to a [`Subscription`] that it hasn't received yet.
+struct Queue<T> {
+    values: VecDeque<T>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// The sending half of a [`Subscription`], owned by the bus.
+struct Sender<T> {
+    queue: Arc<Mutex<Queue<T>>>,
+}
+
+/// A [`Sender`] with the type of the values it sends erased.
+trait Deliver: Send {
+    /// Sends a clone of `value`, which must be of the type of the subscription, and
+    /// returns the waker of the task awaiting it, to be woken once no lock is held.
+    fn deliver(&self, value: &dyn Any) -> Option<Waker>;
+
+    /// Returns `true` exactly if the subscription has been dropped.
+    fn is_closed(&self) -> bool;
+}
+
+impl<T: Clone + Send + 'static> Deliver for Sender<T> {
+    fn deliver(&self, value: &dyn Any) -> Option<Waker> {
+        let value = value
+            .downcast_ref::<T>()
+            .expect("subscriptions are keyed by the type they receive");
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        queue.values.push_back(value.clone());
+        queue.waker.take()
+    }
+
+    fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.queue) == 1
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+            queue.closed = true;
+            queue.waker.take()
+        };
+        // Woken without the lock, the task may poll the subscription right away.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// An asynchronous event bus for values of the enum `E`, which can be shared between tasks
+/// and threads.
+///
+/// Subscriptions are created for one payload type, and every published value is
+/// sent to the subscriptions of the type it holds, as a clone of the value. Queues
+/// are unbounded, so publishing never waits.
+///
+/// ```
+/// # use std::future::Future;
+/// # use std::sync::Arc;
+/// # use std::task::{Context, Poll, Wake, Waker};
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     struct Unpark(std::thread::Thread);
+/// #     impl Wake for Unpark {
+/// #         fn wake(self: Arc<Self>) { self.0.unpark() }
+/// #     }
+/// #     let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+/// #     let mut future = Box::pin(future);
+/// #     loop {
+/// #         match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+/// #             Poll::Ready(output) => return output,
+/// #             Poll::Pending => std::thread::park(),
+/// #         }
+/// #     }
+/// # }
+/// use try_as_traits::bus::AsyncTypedBus;
+///
+/// #[derive(try_as_macros::From, try_as_macros::PayloadAny)]
+/// enum Event {
+///     Tick(u64),
+///     Log(String),
+/// }
+///
+/// let bus = Arc::new(AsyncTypedBus::<Event>::new());
+/// let mut ticks = bus.subscribe::<u64>();
+///
+/// let publisher = Arc::clone(&bus);
+/// let thread = std::thread::spawn(move || {
+///     publisher.publish("starting".to_owned());
+///     publisher.publish(1u64);
+///     publisher.publish(2u64);
+/// });
+///
+/// block_on(async {
+///     assert_eq!(ticks.recv().await, Some(1));
+///     assert_eq!(ticks.recv().await, Some(2));
+/// });
+/// thread.join().unwrap();
+///
+/// drop(bus);
+/// assert_eq!(block_on(ticks.recv()), None);
+/// ```
+pub struct AsyncTypedBus<E> {
+    subscriptions: Mutex<HashMap<TypeId, Vec<Box<dyn Deliver>>>>,
+    enum_: PhantomData<fn(E)>,
+}
+
+impl<E: PayloadAny> AsyncTypedBus<E> {
+    /// Creates a bus without subscriptions.
+    pub fn new() -> Self {
+        AsyncTypedBus {
+            subscriptions: Mutex::new(HashMap::new()),
+            enum_: PhantomData,
+        }
+    }
+
+    /// Creates a subscription receiving the values of type `T` published from now on.
+    pub fn subscribe<T: Clone + Send + 'static>(&self) -> Subscription<T> {
+        let queue = Arc::new(Mutex::new(Queue {
+            values: VecDeque::new(),
+            waker: None,
+            closed: false,
+        }));
+        let sender = Sender {
+            queue: Arc::clone(&queue),
+        };
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(Box::new(sender));
+        Subscription { queue }
+    }
+
+    /// Returns the number of subscriptions for values of type `T` that haven't been dropped.
+    pub fn subscribers_of<T: 'static>(&self) -> usize {
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&TypeId::of::<T>())
+            .map_or(0, |senders| {
+                senders.iter().filter(|s| !s.is_closed()).count()
+            })
+    }
+
+    /// Sends `value` to the subscriptions of the type it holds, and returns their number.
+    pub fn publish(&self, value: impl Into<E>) -> usize {
+        let value = value.into();
+        let payload = value.payload_any();
+        let mut subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (delivered, wakers) = match subscriptions.get_mut(&payload.type_id()) {
+            Some(senders) => {
+                senders.retain(|sender| !sender.is_closed());
+                let wakers: Vec<Waker> = senders
+                    .iter()
+                    .filter_map(|sender| sender.deliver(payload))
+                    .collect();
+                (senders.len(), wakers)
+            }
+            None => return 0,
+        };
+        drop(subscriptions);
+        // Woken without the locks, the tasks may poll or subscribe right away.
+        for waker in wakers {
+            waker.wake();
+        }
+        delivered
+    }
+}
+
+impl<E: PayloadAny> Default for AsyncTypedBus<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The values of type `T` published on an [`AsyncTypedBus`], in the order they were published.
+///
+/// It doesn't implement `futures_core::Stream`, as the crate has no dependencies to
+/// implement it with. [`Subscription::poll_recv`] has the signature of
+/// `Stream::poll_next` for an `Unpin` stream, so a wrapper implementing `Stream` only
+/// needs to forward to it.
+pub struct Subscription<T> {
+    queue: Arc<Mutex<Queue<T>>>,
+}
+
+impl<T> Subscription<T> {
+    /// Receives the next value, waiting until one is published. Returns `None` once the
+    /// bus has been dropped and all values have been received.
+    pub async fn recv(&mut self) -> Option<T> {
+        std::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls for the next value, registering the waker of `cx` if there is none yet,
+    /// e.g. to implement a `Stream` on top of the subscription.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        match queue.values.pop_front() {
+            Some(value) => Poll::Ready(Some(value)),
+            None if queue.closed => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Receives the next value if one has been published, without waiting.
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values
+            .pop_front()
+    }
+}
@@ -0,0 +1,135 @@
+//! A wrapper around [`std::sync::mpsc`] channels carrying enums, with receiving by
+//! type, see [`channel`].
+//!
+//! ```
+//! use try_as_traits::channel::channel;
+//!
+//! #[derive(try_as_macros::From, try_as_macros::TryInto)]
+//! enum Message {
+//!     Job(u32),
+//!     Log(String),
+//! }
+//!
+//! let (sender, mut receiver) = channel::<Message>();
+//! sender.send("starting".to_owned()).unwrap();
+//! sender.send(7u32).unwrap();
+//!
+//! assert_eq!(receiver.recv_as::<u32>(), Ok(7));
+//! assert_eq!(receiver.recv_as::<String>().as_deref(), Ok("starting"));
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, RecvError, SendError, TryRecvError};
+
+/// Creates a new channel for values of the enum `E`, see [`mpsc::channel`].
+pub fn channel<E>() -> (TypedSender<E>, TypedReceiver<E>) {
+    let (sender, receiver) = mpsc::channel();
+    (
+        TypedSender { sender },
+        TypedReceiver {
+            receiver,
+            pending: VecDeque::new(),
+        },
+    )
+}
+
+/// The sending half of a [`channel`].
+pub struct TypedSender<E> {
+    sender: mpsc::Sender<E>,
+}
+
+impl<E> TypedSender<E> {
+    /// Sends `value` as an `E`, see [`mpsc::Sender::send`].
+    pub fn send(&self, value: impl Into<E>) -> Result<(), SendError<E>> {
+        self.sender.send(value.into())
+    }
+}
+
+impl<E> Clone for TypedSender<E> {
+    fn clone(&self) -> Self {
+        TypedSender {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+/// The receiving half of a [`channel`].
+///
+/// Values skipped while receiving by type are kept, and returned by later calls
+/// in the order they were sent.
+pub struct TypedReceiver<E> {
+    receiver: mpsc::Receiver<E>,
+    pending: VecDeque<E>,
+}
+
+impl<E> TypedReceiver<E> {
+    /// Receives the next value, blocking until one is available, see [`mpsc::Receiver::recv`].
+    pub fn recv(&mut self) -> Result<E, RecvError> {
+        match self.pending.pop_front() {
+            Some(value) => Ok(value),
+            None => self.receiver.recv(),
+        }
+    }
+
+    /// Receives the next value, without blocking, see [`mpsc::Receiver::try_recv`].
+    pub fn try_recv(&mut self) -> Result<E, TryRecvError> {
+        match self.pending.pop_front() {
+            Some(value) => Ok(value),
+            None => self.receiver.try_recv(),
+        }
+    }
+
+    /// Receives the next value of type `T`, blocking until one is available.
+    /// Values of other types are kept for later calls.
+    pub fn recv_as<T>(&mut self) -> Result<T, RecvError>
+    where
+        E: TryInto<T, Error = E>,
+    {
+        if let Some(value) = self.take_pending() {
+            return Ok(value);
+        }
+        loop {
+            match self.receiver.recv()?.try_into() {
+                Ok(value) => return Ok(value),
+                Err(other) => self.pending.push_back(other),
+            }
+        }
+    }
+
+    /// Receives the next value of type `T`, without blocking.
+    /// Values of other types are kept for later calls.
+    pub fn try_recv_as<T>(&mut self) -> Result<T, TryRecvError>
+    where
+        E: TryInto<T, Error = E>,
+    {
+        if let Some(value) = self.take_pending() {
+            return Ok(value);
+        }
+        loop {
+            match self.receiver.try_recv()?.try_into() {
+                Ok(value) => return Ok(value),
+                Err(other) => self.pending.push_back(other),
+            }
+        }
+    }
+
+    /// Removes the first pending value of type `T`, keeping the order of the others.
+    fn take_pending<T>(&mut self) -> Option<T>
+    where
+        E: TryInto<T, Error = E>,
+    {
+        let mut found = None;
+        for _ in 0..self.pending.len() {
+            let value = self.pending.pop_front()?;
+            if found.is_some() {
+                self.pending.push_back(value);
+                continue;
+            }
+            match value.try_into() {
+                Ok(value) => found = Some(value),
+                Err(value) => self.pending.push_back(value),
+            }
+        }
+        found
+    }
+}
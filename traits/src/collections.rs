@@ -0,0 +1,621 @@
+//! Collections of type enumerating enums, grouped by the type of the values they hold.
+
+#[cfg(feature = "std")]
+use std::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::sync::{PoisonError, RwLock};
+
+#[cfg(feature = "std")]
+use crate::TryAsMut;
+#[cfg(feature = "std")]
+use crate::VariantInfo;
+use crate::{Introspect, TryAsRef};
+
+/// A subscriber of a [`TypedState`], called with the new value of its slot.
+#[cfg(feature = "std")]
+type Subscriber<'a, E> = Box<dyn FnMut(&E) + 'a>;
+
+/// A collection of arbitrarily many values of the enum `E`, grouped by the type they hold.
+///
+/// Values are kept in one bucket per variant, indexed by [`Introspect::variant_index`],
+/// so accessing all values of one type doesn't touch the others. The insertion
+/// order is kept among values of the same type only.
+///
+/// ```
+/// # use try_as_traits::{Introspect, TryAsRef, VariantInfo};
+/// use try_as_traits::collections::TypedMultiMap;
+/// # use std::any::TypeId;
+/// # enum Message { Click(u32), Key(char) }
+/// # impl From<u32> for Message { fn from(a: u32) -> Self { Message::Click(a) } }
+/// # impl From<char> for Message { fn from(a: char) -> Self { Message::Key(a) } }
+/// # impl TryInto<u32> for Message {
+/// #     type Error = Self;
+/// #     fn try_into(self) -> Result<u32, Self> { if let Message::Click(a) = self { Ok(a) } else { Err(self) } }
+/// # }
+/// # impl TryAsRef<char> for Message {
+/// #     fn try_as_ref(&self) -> Option<&char> { if let Message::Key(a) = self { Some(a) } else { None } }
+/// # }
+/// # impl Introspect for Message {
+/// #     const TYPES: &'static [VariantInfo] = &[
+/// #         VariantInfo { name: "Click", type_name: "u32", type_id: TypeId::of::<u32>(), size: 4, align: 4 },
+/// #         VariantInfo { name: "Key", type_name: "char", type_id: TypeId::of::<char>(), size: 4, align: 4 },
+/// #     ];
+/// #     fn variant_index(&self) -> usize { match self { Message::Click(_) => 0, Message::Key(_) => 1 } }
+/// # }
+///
+/// let mut inbox = TypedMultiMap::<Message>::new();
+/// inbox.insert(1u32);
+/// inbox.insert('a');
+/// inbox.insert(2u32);
+///
+/// assert_eq!(inbox.iter_of::<char>().collect::<Vec<_>>(), [&'a']);
+/// assert_eq!(inbox.drain_of::<u32>().collect::<Vec<_>>(), [1, 2]);
+/// assert_eq!(inbox.count_of::<u32>(), 0);
+/// assert_eq!(inbox.len(), 1);
+/// ```
+#[cfg(feature = "std")]
+pub struct TypedMultiMap<E> {
+    buckets: Vec<Vec<E>>,
+}
+
+#[cfg(feature = "std")]
+impl<E: Introspect> TypedMultiMap<E> {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        TypedMultiMap {
+            buckets: E::TYPES.iter().map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Adds a value to the collection.
+    pub fn insert(&mut self, value: impl Into<E>) {
+        let value = value.into();
+        self.buckets[value.variant_index()].push(value);
+    }
+
+    /// Returns the number of values in the collection.
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(Vec::len).sum()
+    }
+
+    /// Returns `true` exactly if the collection holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(Vec::is_empty)
+    }
+
+    /// Returns the number of values of type `T`.
+    pub fn count_of<T: 'static>(&self) -> usize {
+        E::index_of_type::<T>().map_or(0, |i| self.buckets[i].len())
+    }
+
+    /// Returns the number of values of each type, in declaration order of the variants.
+    pub fn counts(&self) -> impl Iterator<Item = (&'static VariantInfo, usize)> + '_ {
+        E::TYPES
+            .iter()
+            .zip(&self.buckets)
+            .map(|(info, bucket)| (info, bucket.len()))
+    }
+
+    /// Iterates over all values, grouped by type.
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.buckets.iter().flatten()
+    }
+
+    /// Iterates over the values of type `T`, in insertion order.
+    pub fn iter_of<T: 'static>(&self) -> impl Iterator<Item = &T>
+    where
+        E: TryAsRef<T>,
+    {
+        E::index_of_type::<T>()
+            .map(|i| &self.buckets[i][..])
+            .unwrap_or_default()
+            .iter()
+            .filter_map(TryAsRef::try_as_ref)
+    }
+
+    /// Removes the values of type `T` from the collection and iterates over them, in
+    /// insertion order.
+    pub fn drain_of<T: 'static>(&mut self) -> impl Iterator<Item = T>
+    where
+        E: TryInto<T>,
+    {
+        E::index_of_type::<T>()
+            .map(|i| std::mem::take(&mut self.buckets[i]))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|value| value.try_into().ok())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Introspect> Default for TypedMultiMap<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Introspect> Extend<E> for TypedMultiMap<E> {
+    fn extend<I: IntoIterator<Item = E>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Introspect> FromIterator<E> for TypedMultiMap<E> {
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// The latest value of each type of the enum `E`, notifying subscribers when they change.
+///
+/// There is one slot per variant, indexed by [`Introspect::variant_index`]. Setting a
+/// value replaces the value in its slot, and calls the subscribers of its type with
+/// it, whether or not it differs from the previous one.
+///
+/// ```
+/// use try_as_traits::collections::TypedState;
+///
+/// #[derive(try_as_macros::From, try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::Introspect)]
+/// enum Reading {
+///     Temperature(f32),
+///     Online(bool),
+/// }
+///
+/// let mut changes = Vec::new();
+/// let mut state = TypedState::<Reading>::new();
+/// state.subscribe(|temperature: &f32| changes.push(*temperature));
+///
+/// state.set(21.5f32);
+/// state.set(true);
+/// state.set(22.0f32);
+/// assert_eq!(state.get::<f32>(), Some(&22.0));
+/// assert_eq!(state.remove::<bool>(), Some(true));
+/// assert_eq!(state.get::<bool>(), None);
+///
+/// drop(state);
+/// assert_eq!(changes, [21.5, 22.0]);
+/// ```
+#[cfg(feature = "std")]
+pub struct TypedState<'a, E> {
+    slots: Vec<Option<E>>,
+    subscribers: Vec<Vec<Subscriber<'a, E>>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, E: Introspect> TypedState<'a, E> {
+    /// Creates a state with all slots empty and no subscribers.
+    pub fn new() -> Self {
+        TypedState {
+            slots: E::TYPES.iter().map(|_| None).collect(),
+            subscribers: E::TYPES.iter().map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Replaces the value in the slot of the type of `value`, and notifies the subscribers
+    /// of that type. Returns the value that was replaced.
+    pub fn set(&mut self, value: impl Into<E>) -> Option<E> {
+        let value = value.into();
+        let index = value.variant_index();
+        for subscriber in &mut self.subscribers[index] {
+            subscriber(&value);
+        }
+        self.slots[index].replace(value)
+    }
+
+    /// Returns the latest value of type `T`, if any.
+    pub fn get<T: 'static>(&self) -> Option<&T>
+    where
+        E: TryAsRef<T>,
+    {
+        E::index_of_type::<T>()
+            .and_then(|i| self.slots[i].as_ref())
+            .and_then(TryAsRef::try_as_ref)
+    }
+
+    /// Removes the latest value of type `T` and returns it, if any. Subscribers aren't notified.
+    pub fn remove<T: 'static>(&mut self) -> Option<T>
+    where
+        E: TryInto<T>,
+    {
+        E::index_of_type::<T>()
+            .and_then(|i| self.slots[i].take())
+            .and_then(|value| value.try_into().ok())
+    }
+
+    /// Registers `subscriber` to be called with every value of type `T` that is set.
+    ///
+    /// # Panics
+    ///
+    /// If `T` isn't one of the types of `E`, which can only happen with hand-written
+    /// implementations of `TryAsRef<T>`.
+    pub fn subscribe<T: 'static>(&mut self, mut subscriber: impl FnMut(&T) + 'a) -> &mut Self
+    where
+        E: TryAsRef<T>,
+    {
+        let index = E::index_of_type::<T>().expect("`E` holds values of type `T`");
+        self.subscribers[index].push(Box::new(move |value| {
+            if let Some(value) = value.try_as_ref() {
+                subscriber(value)
+            }
+        }));
+        self
+    }
+
+    /// Returns the slot of type `T`, to insert or modify its value in place, like
+    /// [`HashMap::entry`](std::collections::HashMap::entry).
+    ///
+    /// ```
+    /// use try_as_traits::collections::TypedState;
+    /// use try_as_traits::TryAsMut;
+    ///
+    /// #[derive(try_as_macros::From, try_as_macros::TryAsMut, try_as_macros::Introspect)]
+    /// enum Cached {
+    ///     Hits(u64),
+    ///     Name(String),
+    /// }
+    ///
+    /// let mut state = TypedState::<Cached>::new();
+    /// state.entry::<u64>().and_modify(|hits| *hits += 1).or_insert(1);
+    /// state.entry::<u64>().and_modify(|hits| *hits += 1).or_insert(1);
+    /// assert_eq!(*state.entry::<u64>().or_insert(0), 2);
+    ///
+    /// let name = state.entry::<String>().or_insert_with(|| "cache".to_owned());
+    /// name.push_str("-1");
+    /// assert_eq!(state.entry::<String>().or_insert_with(String::new), "cache-1");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `T` isn't one of the types of `E`, which can only happen with hand-written
+    /// implementations of `From<T>`.
+    pub fn entry<T: 'static>(&mut self) -> Entry<'_, 'a, E, T>
+    where
+        E: From<T> + TryAsMut<T>,
+    {
+        let index = E::index_of_type::<T>().expect("`E` holds values of type `T`");
+        Entry {
+            state: self,
+            index,
+            type_: PhantomData,
+        }
+    }
+
+    /// Moves the values of `other` into `self`, slot by slot. Values of types only set in
+    /// `other` are taken over, and for types set in both, `resolve` decides which value
+    /// to keep. The subscribers of the types whose value changes are notified, those of
+    /// `other` are dropped.
+    ///
+    /// ```
+    /// use try_as_traits::collections::{Resolution, TypedState};
+    ///
+    /// #[derive(Debug, PartialEq, try_as_macros::From, try_as_macros::TryAsRef, try_as_macros::Introspect)]
+    /// enum Setting {
+    ///     Retries(u32),
+    ///     Motd(String),
+    ///     Verbose(bool),
+    /// }
+    ///
+    /// let mut config = TypedState::<Setting>::new();
+    /// config.set(3u32);
+    /// config.set("Hello".to_owned());
+    ///
+    /// let mut overrides = TypedState::<Setting>::new();
+    /// overrides.set(5u32);
+    /// overrides.set("Bye".to_owned());
+    /// overrides.set(true);
+    ///
+    /// config.merge_with(overrides, |pair| match (pair.ours, pair.theirs) {
+    ///     (Setting::Retries(ours), Setting::Retries(theirs)) => {
+    ///         Resolution::Replace(Setting::Retries(*ours.max(theirs)))
+    ///     }
+    ///     _ => Resolution::KeepOurs,
+    /// });
+    /// assert_eq!(config.get::<u32>(), Some(&5));
+    /// assert_eq!(config.get::<String>().map(String::as_str), Some("Hello"));
+    /// assert_eq!(config.get::<bool>(), Some(&true));
+    /// ```
+    pub fn merge_with(
+        &mut self,
+        other: TypedState<'_, E>,
+        mut resolve: impl FnMut(KindedPair<'_, E>) -> Resolution<E>,
+    ) {
+        for (index, theirs) in other.slots.into_iter().enumerate() {
+            let Some(theirs) = theirs else {
+                continue;
+            };
+            let merged = match &self.slots[index] {
+                None => theirs,
+                Some(ours) => match resolve(KindedPair {
+                    ours,
+                    theirs: &theirs,
+                }) {
+                    Resolution::KeepOurs => continue,
+                    Resolution::TakeTheirs => theirs,
+                    Resolution::Replace(value) => value,
+                },
+            };
+            assert_eq!(
+                merged.variant_index(),
+                index,
+                "the resolved value must be of the type of its slot"
+            );
+            self.slots[index] = Some(merged);
+            self.notify(index);
+        }
+    }
+
+    /// Calls the subscribers of the slot at `index` with its value, if any.
+    fn notify(&mut self, index: usize) {
+        if let Some(value) = &self.slots[index] {
+            for subscriber in &mut self.subscribers[index] {
+                subscriber(value);
+            }
+        }
+    }
+
+    /// Iterates over the latest value of each type that has been set, in declaration order
+    /// of the variants.
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.slots.iter().flatten()
+    }
+}
+
+/// The values of the same type in two [`TypedState`]s, passed to the callback of
+/// [`TypedState::merge_with`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct KindedPair<'p, E> {
+    /// The value of the state merged into.
+    pub ours: &'p E,
+    /// The value of the state merged from.
+    pub theirs: &'p E,
+}
+
+/// The value [`TypedState::merge_with`] keeps for a type set in both states.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution<E> {
+    /// Keeps the value of the state merged into.
+    KeepOurs,
+    /// Takes the value of the state merged from.
+    TakeTheirs,
+    /// Replaces both with another value, which must be of the same type.
+    Replace(E),
+}
+
+/// The slot of the type `T` in a [`TypedState`], returned by [`TypedState::entry`].
+///
+/// Inserting or modifying a value notifies the subscribers of `T`, like
+/// [`TypedState::set`].
+#[cfg(feature = "std")]
+pub struct Entry<'s, 'a, E, T> {
+    state: &'s mut TypedState<'a, E>,
+    index: usize,
+    type_: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "std")]
+impl<'s, E: Introspect + From<T> + TryAsMut<T>, T: 'static> Entry<'s, '_, E, T> {
+    /// Calls `f` with the value of type `T`, if any.
+    pub fn and_modify(self, f: impl FnOnce(&mut T)) -> Self {
+        if let Some(value) = self.state.slots[self.index]
+            .as_mut()
+            .and_then(TryAsMut::try_as_mut)
+        {
+            f(value);
+            self.state.notify(self.index);
+        }
+        self
+    }
+
+    /// Returns the value of type `T`, inserting `default` if there is none.
+    pub fn or_insert(self, default: T) -> &'s mut T {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns the value of type `T`, inserting the result of `default` if there is none.
+    pub fn or_insert_with(self, default: impl FnOnce() -> T) -> &'s mut T {
+        if self.state.slots[self.index].is_none() {
+            self.state.slots[self.index] = Some(E::from(default()));
+            self.state.notify(self.index);
+        }
+        self.state.slots[self.index]
+            .as_mut()
+            .and_then(TryAsMut::try_as_mut)
+            .expect("the slot of `T` holds a `T`")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Introspect> Default for TypedState<'_, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The latest value of each type of the enum `E`, like [`TypedState`] without subscribers,
+/// shared between threads.
+///
+/// Each slot has its own lock, so accessing the value of one type never waits for
+/// accesses to the values of other types.
+///
+/// ```
+/// use try_as_traits::collections::ConcurrentTypedState;
+///
+/// #[derive(try_as_macros::From, try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::Introspect)]
+/// enum Setting {
+///     Retries(u32),
+///     Motd(String),
+/// }
+///
+/// let state = ConcurrentTypedState::<Setting>::new();
+/// std::thread::scope(|scope| {
+///     scope.spawn(|| state.insert(3u32));
+///     scope.spawn(|| state.insert("Hello".to_owned()));
+/// });
+///
+/// assert_eq!(state.get_cloned::<u32>(), Some(3));
+/// assert_eq!(state.with(|motd: Option<&String>| motd.map(String::len)), Some(5));
+/// assert_eq!(state.remove::<String>().as_deref(), Some("Hello"));
+/// ```
+#[cfg(feature = "std")]
+pub struct ConcurrentTypedState<E> {
+    slots: Vec<RwLock<Option<E>>>,
+}
+
+#[cfg(feature = "std")]
+impl<E: Introspect> ConcurrentTypedState<E> {
+    /// Creates a state with all slots empty.
+    pub fn new() -> Self {
+        ConcurrentTypedState {
+            slots: E::TYPES.iter().map(|_| RwLock::new(None)).collect(),
+        }
+    }
+
+    /// Replaces the value in the slot of the type of `value`, and returns the value that
+    /// was replaced.
+    pub fn insert(&self, value: impl Into<E>) -> Option<E> {
+        let value = value.into();
+        let mut slot = self.slots[value.variant_index()]
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        slot.replace(value)
+    }
+
+    /// Returns a clone of the latest value of type `T`, if any.
+    pub fn get_cloned<T: Clone + 'static>(&self) -> Option<T>
+    where
+        E: TryAsRef<T>,
+    {
+        self.with(|value: Option<&T>| value.cloned())
+    }
+
+    /// Calls `f` with the latest value of type `T`, if any, and returns its result. Values
+    /// of type `T` can't be replaced while `f` runs.
+    pub fn with<T: 'static, R>(&self, f: impl FnOnce(Option<&T>) -> R) -> R
+    where
+        E: TryAsRef<T>,
+    {
+        match E::index_of_type::<T>() {
+            Some(i) => {
+                let slot = self.slots[i].read().unwrap_or_else(PoisonError::into_inner);
+                f(slot.as_ref().and_then(TryAsRef::try_as_ref))
+            }
+            None => f(None),
+        }
+    }
+
+    /// Removes the latest value of type `T` and returns it, if any.
+    pub fn remove<T: 'static>(&self) -> Option<T>
+    where
+        E: TryInto<T>,
+    {
+        let i = E::index_of_type::<T>()?;
+        let value = self.slots[i]
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        value.and_then(|value| value.try_into().ok())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Introspect> Default for ConcurrentTypedState<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The latest value of each type of the enum `E`, like [`TypedState`] without subscribers,
+/// kept in an array of `N` slots instead of on the heap. `N` must be
+/// [`Introspect::VARIANT_COUNT`]. Unlike the other collections, it's available without
+/// the `std` feature.
+///
+/// ```
+/// use try_as_traits::collections::ArrayTypedState;
+/// use try_as_traits::Introspect;
+///
+/// #[derive(try_as_macros::From, try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::Introspect)]
+/// enum Reading {
+///     Temperature(i16),
+///     Online(bool),
+/// }
+///
+/// let mut state = ArrayTypedState::<Reading, { Reading::VARIANT_COUNT }>::new();
+/// state.set(215i16);
+/// state.set(true);
+/// assert_eq!(state.get::<i16>(), Some(&215));
+/// assert_eq!(state.remove::<bool>(), Some(true));
+/// assert_eq!(state.iter().count(), 1);
+/// ```
+pub struct ArrayTypedState<E, const N: usize> {
+    slots: [Option<E>; N],
+}
+
+impl<E: Introspect, const N: usize> ArrayTypedState<E, N> {
+    /// Creates a state with all slots empty. Fails to compile if `N` isn't
+    /// [`Introspect::VARIANT_COUNT`].
+    ///
+    /// ```compile_fail,E0080
+    /// use try_as_traits::collections::ArrayTypedState;
+    ///
+    /// #[derive(try_as_macros::Introspect)]
+    /// enum Reading {
+    ///     Temperature(i16),
+    ///     Online(bool),
+    /// }
+    ///
+    /// let state = ArrayTypedState::<Reading, 3>::new();
+    /// ```
+    pub fn new() -> Self {
+        const { assert!(N == E::VARIANT_COUNT, "`N` must be the number of variants") };
+        ArrayTypedState {
+            slots: core::array::from_fn(|_| None),
+        }
+    }
+
+    /// Replaces the value in the slot of the type of `value`, and returns the value that
+    /// was replaced.
+    pub fn set(&mut self, value: impl Into<E>) -> Option<E> {
+        let value = value.into();
+        self.slots[value.variant_index()].replace(value)
+    }
+
+    /// Returns the latest value of type `T`, if any.
+    pub fn get<T: 'static>(&self) -> Option<&T>
+    where
+        E: TryAsRef<T>,
+    {
+        E::index_of_type::<T>()
+            .and_then(|i| self.slots[i].as_ref())
+            .and_then(TryAsRef::try_as_ref)
+    }
+
+    /// Removes the latest value of type `T` and returns it, if any.
+    pub fn remove<T: 'static>(&mut self) -> Option<T>
+    where
+        E: TryInto<T>,
+    {
+        E::index_of_type::<T>()
+            .and_then(|i| self.slots[i].take())
+            .and_then(|value| value.try_into().ok())
+    }
+
+    /// Iterates over the latest value of each type that has been set, in declaration order
+    /// of the variants.
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.slots.iter().flatten()
+    }
+}
+
+impl<E: Introspect, const N: usize> Default for ArrayTypedState<E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,181 @@
+//! Converting the values held by enums between their types along registered
+//! conversions, see [`Conversions`] and [`ToVariant`].
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+
+use crate::Registrable;
+
+/// A conversion of a [`Conversions`] with its argument and result types erased.
+type Converter = Box<dyn Fn(Box<dyn Any>) -> Box<dyn Any> + Send + Sync>;
+
+/// A graph of conversions between types, e.g. `i64` to `f64` or `String` to `PathBuf`.
+///
+/// A value is converted to a type along the shortest chain of registered conversions
+/// leading there, so registering `i32` to `i64` and `i64` to `f64` converts `i32` to
+/// `f64` as well.
+///
+/// ```
+/// use try_as_traits::convert::Conversions;
+///
+/// let mut conversions = Conversions::new();
+/// conversions
+///     .register(|n: i32| n as i64)
+///     .register(|n: i64| n as f64);
+///
+/// assert_eq!(conversions.convert::<i32, f64>(3), Ok(3.0));
+/// assert_eq!(conversions.convert::<f64, i32>(3.0), Err(3.0));
+/// ```
+#[derive(Default)]
+pub struct Conversions {
+    edges: HashMap<TypeId, Vec<(TypeId, Converter)>>,
+}
+
+impl Conversions {
+    /// Creates a graph without conversions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `convert` to convert values of type `A` to type `B`, replacing any
+    /// previous conversion from `A` to `B`.
+    pub fn register<A: 'static, B: 'static>(
+        &mut self,
+        convert: impl Fn(A) -> B + Send + Sync + 'static,
+    ) -> &mut Self {
+        let converter: Converter = Box::new(move |value| {
+            Box::new(convert(
+                *value
+                    .downcast()
+                    .expect("conversions are keyed by the type they convert"),
+            ))
+        });
+        let edges = self.edges.entry(TypeId::of::<A>()).or_default();
+        edges.retain(|(to, _)| *to != TypeId::of::<B>());
+        edges.push((TypeId::of::<B>(), converter));
+        self
+    }
+
+    /// Returns `true` exactly if values of type `A` can be converted to type `B`.
+    pub fn can_convert<A: 'static, B: 'static>(&self) -> bool {
+        self.path(TypeId::of::<A>(), TypeId::of::<B>()).is_some()
+    }
+
+    /// Converts `value` to type `B`, handing it back if there is no chain of
+    /// conversions leading there.
+    pub fn convert<A: 'static, B: 'static>(&self, value: A) -> Result<B, A> {
+        match self.convert_any(Box::new(value), TypeId::of::<B>()) {
+            Ok(value) => Ok(*value.downcast().expect("the path ends at `B`")),
+            Err(value) => Err(*value.downcast().expect("the value is handed back")),
+        }
+    }
+
+    /// Converts the type-erased `value` to the type with the given [`TypeId`], handing
+    /// it back if there is no chain of conversions leading there.
+    pub fn convert_any(
+        &self,
+        value: Box<dyn Any>,
+        to: TypeId,
+    ) -> Result<Box<dyn Any>, Box<dyn Any>> {
+        let path = match self.path((*value).type_id(), to) {
+            Some(path) => path,
+            None => return Err(value),
+        };
+        Ok(path
+            .into_iter()
+            .fold(value, |value, convert| convert(value)))
+    }
+
+    /// Returns the conversions along the shortest chain from `from` to `to`.
+    fn path(&self, from: TypeId, to: TypeId) -> Option<Vec<&Converter>> {
+        let mut previous: HashMap<TypeId, (TypeId, &Converter)> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(type_id) = queue.pop_front() {
+            if type_id == to {
+                let mut path = Vec::new();
+                let mut type_id = to;
+                while type_id != from {
+                    let (before, convert) = previous[&type_id];
+                    path.push(convert);
+                    type_id = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, convert) in self.edges.get(&type_id).into_iter().flatten() {
+                if *next != from && !previous.contains_key(next) {
+                    previous.insert(*next, (type_id, convert));
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Converts the value held by an enum to another of the types it can hold, along the
+/// [`Conversions`] registered between them.
+///
+/// Implemented for all enums deriving `Registrable` and `IntoBoxedAny`.
+///
+/// ```
+/// use std::path::PathBuf;
+/// use try_as_traits::convert::{Conversions, ToVariant};
+///
+/// #[derive(Debug, PartialEq, try_as_macros::Registrable, try_as_macros::IntoBoxedAny)]
+/// enum Value {
+///     Int(i64),
+///     Float(f64),
+///     Text(String),
+///     Path(PathBuf),
+/// }
+///
+/// let mut conversions = Conversions::new();
+/// conversions
+///     .register(|n: i64| n as f64)
+///     .register(<PathBuf as From<String>>::from);
+///
+/// let value = Value::Int(2).to_variant::<f64>(&conversions);
+/// assert_eq!(value, Ok(Value::Float(2.0)));
+///
+/// let value = Value::Text("/tmp".to_owned()).to_variant::<PathBuf>(&conversions);
+/// assert_eq!(value, Ok(Value::Path(PathBuf::from("/tmp"))));
+///
+/// let value = Value::Float(2.0).to_variant::<i64>(&conversions);
+/// assert_eq!(value, Err(Value::Float(2.0)));
+/// ```
+pub trait ToVariant: Sized {
+    /// Converts the held value to type `T`, and returns the variant holding it. If the
+    /// enum can't hold `T` or there is no chain of conversions leading there, `self` is
+    /// handed back. Values already of type `T` are returned unchanged.
+    fn to_variant<T: 'static>(self, conversions: &Conversions) -> Result<Self, Self>;
+}
+
+impl<E> ToVariant for E
+where
+    E: Registrable + Into<Box<dyn Any + Send + Sync>>,
+{
+    fn to_variant<T: 'static>(self, conversions: &Conversions) -> Result<Self, Self> {
+        let entries = E::registry_entries();
+        let construct = |value: Box<dyn Any>| {
+            let type_id = (*value).type_id();
+            let entry = entries
+                .iter()
+                .find(|entry| entry.type_id == type_id)
+                .expect("the enum holds values of this type");
+            (entry.construct)(value)
+                .unwrap_or_else(|_| unreachable!("the entry constructs values of its type"))
+        };
+        if !entries
+            .iter()
+            .any(|entry| entry.type_id == TypeId::of::<T>())
+        {
+            return Err(self);
+        }
+        let value: Box<dyn Any> = self.into();
+        match conversions.convert_any(value, TypeId::of::<T>()) {
+            Ok(value) => Ok(construct(value)),
+            Err(value) => Err(construct(value)),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Estimating the memory used by values including their heap allocations, see
+//! [`DeepSize`].
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::mem::size_of;
+use std::path::PathBuf;
+
+/// A trait for values whose memory use, including the heap allocations they own, can
+/// be estimated, e.g. for capacity planning of caches.
+///
+/// Allocations are counted with their capacity, not their length. Maps and sets are
+/// estimated from their number of entries, not counting the bookkeeping of their
+/// implementation. Enums holding nested values of themselves, e.g. in a `Vec` or a
+/// `Box`, recurse into them.
+///
+/// ```
+/// use std::mem::size_of;
+/// use try_as_traits::deep_size::DeepSize;
+///
+/// #[derive(try_as_macros::DeepSize)]
+/// enum Value {
+///     Number(i64),
+///     Text(String),
+///     List(Vec<Value>),
+/// }
+///
+/// assert_eq!(Value::Number(1).deep_size(), size_of::<Value>());
+///
+/// let text = String::with_capacity(16);
+/// assert_eq!(Value::Text(text).deep_size(), size_of::<Value>() + 16);
+///
+/// let list = Value::List(vec![Value::Number(1), Value::Text("hi".to_owned())]);
+/// assert_eq!(list.deep_size(), 3 * size_of::<Value>() + 2);
+/// ```
+pub trait DeepSize {
+    /// Returns the number of bytes of the heap allocations owned by `self`.
+    fn heap_size(&self) -> usize;
+
+    /// Returns the number of bytes of `self`, including its heap allocations.
+    fn deep_size(&self) -> usize {
+        std::mem::size_of_val(self) + self.heap_size()
+    }
+}
+
+macro_rules! impl_without_heap {
+    ($($type_:ty),* $(,)?) => {
+        $(
+            impl DeepSize for $type_ {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_without_heap!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    &str,
+);
+
+impl DeepSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl DeepSize for PathBuf {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: DeepSize> DeepSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        (**self).deep_size()
+    }
+}
+
+impl<T: DeepSize> DeepSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+impl<T: DeepSize> DeepSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: DeepSize> DeepSize for VecDeque<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: DeepSize, S> DeepSize for HashSet<T, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: DeepSize> DeepSize for BTreeSet<T> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<K: DeepSize, V: DeepSize, S> DeepSize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<(K, V)>() + entries_heap_size(self)
+    }
+}
+
+impl<K: DeepSize, V: DeepSize> DeepSize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<(K, V)>() + entries_heap_size(self)
+    }
+}
+
+/// Returns the number of bytes of the heap allocations owned by the keys and values of a map.
+fn entries_heap_size<'a, K: DeepSize + 'a, V: DeepSize + 'a>(
+    map: impl IntoIterator<Item = (&'a K, &'a V)>,
+) -> usize {
+    map.into_iter()
+        .map(|(key, value)| key.heap_size() + value.heap_size())
+        .sum()
+}
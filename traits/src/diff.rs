@@ -0,0 +1,87 @@
+//! Structural differences between values of nested enums, see [`Diff`].
+
+use std::collections::BTreeMap;
+
+/// The difference between two values of an enum, returned by [`Diff::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueDiff {
+    /// The values are equal.
+    Same,
+    /// The values are held by different variants, given by their identifiers.
+    TypeChanged {
+        /// The variant holding this value.
+        from: &'static str,
+        /// The variant holding the other value.
+        to: &'static str,
+    },
+    /// The values are held by the same variant, but differ.
+    ValueChanged,
+    /// The values are lists or maps of the enum, and these of their entries differ,
+    /// labeled with their index like `[0]` or their key.
+    Nested(Vec<(String, ValueDiff)>),
+    /// The entry exists in the other value only.
+    Added,
+    /// The entry exists in this value only.
+    Removed,
+}
+
+impl ValueDiff {
+    /// Returns `true` exactly if the values are equal.
+    pub fn is_same(&self) -> bool {
+        *self == ValueDiff::Same
+    }
+}
+
+/// A trait for enums whose values can be compared structurally, recursing into lists
+/// and maps of the enum.
+pub trait Diff {
+    /// Returns how `other` differs from `self`.
+    #[must_use]
+    fn diff(&self, other: &Self) -> ValueDiff;
+}
+
+/// Compares two lists entry by entry, see [`Diff::diff`].
+pub fn diff_lists<E: Diff>(from: &[E], to: &[E]) -> ValueDiff {
+    let len = from.len().max(to.len());
+    let entries = (0..len).filter_map(|i| {
+        let diff = match (from.get(i), to.get(i)) {
+            (Some(a), Some(b)) => a.diff(b),
+            (Some(_), None) => ValueDiff::Removed,
+            (None, _) => ValueDiff::Added,
+        };
+        (!diff.is_same()).then(|| (format!("[{}]", i), diff))
+    });
+    nested(entries.collect())
+}
+
+/// Compares two maps entry by entry, in the order of their keys, see [`Diff::diff`].
+pub fn diff_maps<'a, E: Diff + 'a>(
+    from: impl IntoIterator<Item = (&'a String, &'a E)>,
+    to: impl IntoIterator<Item = (&'a String, &'a E)>,
+) -> ValueDiff {
+    let mut entries: BTreeMap<&String, (Option<&E>, Option<&E>)> = BTreeMap::new();
+    for (key, value) in from {
+        entries.entry(key).or_default().0 = Some(value);
+    }
+    for (key, value) in to {
+        entries.entry(key).or_default().1 = Some(value);
+    }
+    let entries = entries.into_iter().filter_map(|(key, values)| {
+        let diff = match values {
+            (Some(a), Some(b)) => a.diff(b),
+            (Some(_), None) => ValueDiff::Removed,
+            (None, _) => ValueDiff::Added,
+        };
+        (!diff.is_same()).then(|| (key.clone(), diff))
+    });
+    nested(entries.collect())
+}
+
+/// Returns [`ValueDiff::Same`] if no entries differ, or the differing ones otherwise.
+fn nested(entries: Vec<(String, ValueDiff)>) -> ValueDiff {
+    if entries.is_empty() {
+        ValueDiff::Same
+    } else {
+        ValueDiff::Nested(entries)
+    }
+}
@@ -0,0 +1,177 @@
+//! Routing values of enums to handlers registered per type, see [`Handlers`] and [`TypedBus`].
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::PayloadAny;
+
+/// A handler with its argument type erased.
+type Handler<R> = Box<dyn Fn(&dyn Any) -> R + Send + Sync>;
+
+/// A subscriber of a [`TypedBus`] with its argument type erased.
+type Subscriber<'a> = Box<dyn FnMut(&dyn Any) + 'a>;
+
+/// A table of handlers, one per type, each returning an `R`.
+///
+/// Handlers can be registered independently of the enums they will handle, e.g.
+/// from different crates, and are looked up by the [`TypeId`] of the value at hand.
+///
+/// ```
+/// use try_as_traits::dispatch::{Dispatch, Handlers};
+///
+/// #[derive(try_as_macros::PayloadAny)]
+/// enum Event {
+///     Click((i32, i32)),
+///     Key(char),
+///     Resize(u32),
+/// }
+///
+/// let mut handlers = Handlers::new();
+/// handlers
+///     .register(|&(x, y): &(i32, i32)| format!("click at {}, {}", x, y))
+///     .register(|key: &char| format!("key {}", key));
+///
+/// assert_eq!(Event::Key('a').dispatch(&handlers).as_deref(), Some("key a"));
+/// assert_eq!(Event::Resize(800).dispatch(&handlers), None);
+/// ```
+pub struct Handlers<R> {
+    handlers: HashMap<TypeId, Handler<R>>,
+}
+
+impl<R> Handlers<R> {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Handlers {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Registers `handler` for values of type `T`, replacing any previous handler of `T`.
+    pub fn register<T: 'static>(
+        &mut self,
+        handler: impl Fn(&T) -> R + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.handlers.insert(
+            TypeId::of::<T>(),
+            Box::new(move |value| {
+                handler(
+                    value
+                        .downcast_ref()
+                        .expect("handlers are keyed by the type they handle"),
+                )
+            }),
+        );
+        self
+    }
+
+    /// Returns `true` exactly if a handler of type `T` has been registered.
+    pub fn handles<T: 'static>(&self) -> bool {
+        self.handlers.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Calls the handler of the type of `value`, if any.
+    pub fn call(&self, value: &dyn Any) -> Option<R> {
+        self.handlers
+            .get(&value.type_id())
+            .map(|handler| handler(value))
+    }
+}
+
+impl<R> Default for Handlers<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Routes the value held by an enum to the matching handler of a [`Handlers`] table.
+/// Implemented for all types implementing [`PayloadAny`].
+pub trait Dispatch {
+    /// Calls the handler of the type of the contained value, if any.
+    fn dispatch<R>(&self, handlers: &Handlers<R>) -> Option<R>;
+}
+
+impl<E: PayloadAny + ?Sized> Dispatch for E {
+    fn dispatch<R>(&self, handlers: &Handlers<R>) -> Option<R> {
+        handlers.call(self.payload_any())
+    }
+}
+
+/// A synchronous event bus for values of the enum `E`.
+///
+/// Subscribers register for one payload type, and every published value is passed
+/// to the subscribers of the type it holds, in subscription order.
+///
+/// ```
+/// use try_as_traits::dispatch::TypedBus;
+/// use std::cell::Cell;
+///
+/// #[derive(try_as_macros::From, try_as_macros::PayloadAny)]
+/// enum Event {
+///     Click((i32, i32)),
+///     Key(char),
+/// }
+///
+/// let keys = Cell::new(0);
+/// let mut bus = TypedBus::<Event>::new();
+/// bus.subscribe(|_: &char| keys.set(keys.get() + 1));
+///
+/// assert_eq!(bus.publish('a'), 1);
+/// assert_eq!(bus.publish(Event::Click((0, 0))), 0);
+/// assert_eq!(keys.get(), 1);
+/// ```
+pub struct TypedBus<'a, E> {
+    subscribers: HashMap<TypeId, Vec<Subscriber<'a>>>,
+    enum_: PhantomData<fn(E)>,
+}
+
+impl<'a, E: PayloadAny> TypedBus<'a, E> {
+    /// Creates a bus without subscribers.
+    pub fn new() -> Self {
+        TypedBus {
+            subscribers: HashMap::new(),
+            enum_: PhantomData,
+        }
+    }
+
+    /// Registers `subscriber` for values of type `T`.
+    pub fn subscribe<T: 'static>(&mut self, mut subscriber: impl FnMut(&T) + 'a) -> &mut Self {
+        self.subscribers
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(Box::new(move |value| {
+                subscriber(
+                    value
+                        .downcast_ref()
+                        .expect("subscribers are keyed by the type they handle"),
+                )
+            }));
+        self
+    }
+
+    /// Returns the number of subscribers for values of type `T`.
+    pub fn subscribers_of<T: 'static>(&self) -> usize {
+        self.subscribers.get(&TypeId::of::<T>()).map_or(0, Vec::len)
+    }
+
+    /// Passes `value` to the subscribers of the type it holds, and returns their number.
+    pub fn publish(&mut self, value: impl Into<E>) -> usize {
+        let value = value.into();
+        let payload = value.payload_any();
+        match self.subscribers.get_mut(&payload.type_id()) {
+            Some(subscribers) => {
+                for subscriber in subscribers.iter_mut() {
+                    subscriber(payload);
+                }
+                subscribers.len()
+            }
+            None => 0,
+        }
+    }
+}
+
+impl<E: PayloadAny> Default for TypedBus<'_, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,291 @@
+//! A compact binary encoding of enums, as the tag of the variant followed by the
+//! encoded value, see [`WireEncode`] and [`WireDecode`].
+//!
+//! Tags and lengths are encoded as LEB128 varints, and so are integers, after
+//! zigzag-encoding signed ones. Floats are encoded as their little-endian bytes,
+//! strings and vectors as their length followed by their elements. Other types
+//! plug in by implementing the traits themselves.
+//!
+//! ```
+//! use try_as_traits::encoding::{DecodeError, WireDecode, WireEncode};
+//!
+//! #[derive(Debug, PartialEq, try_as_macros::WireEncode, try_as_macros::WireDecode)]
+//! enum Value {
+//!     #[try_as(tag = 1)]
+//!     Number(i64),
+//!     #[try_as(tag = 2)]
+//!     String(String),
+//! }
+//!
+//! let bytes = Value::Number(-2).to_wire();
+//! assert_eq!(bytes, [1, 3]);
+//! assert_eq!(Value::from_wire(&bytes), Ok(Value::Number(-2)));
+//! assert_eq!(Value::from_wire(&[3, 0]), Err(DecodeError::InvalidTag(3)));
+//! ```
+
+use std::fmt;
+
+/// The error returned if bytes can't be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The bytes ended before the value.
+    UnexpectedEnd,
+    /// Bytes were left after the value.
+    TrailingBytes,
+    /// A varint doesn't fit the type it is decoded as.
+    Overflow,
+    /// The tag names no variant.
+    InvalidTag(u64),
+    /// The bytes don't encode a valid value of the type, e.g. invalid UTF-8 for a `String`.
+    InvalidValue(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after the value"),
+            DecodeError::Overflow => write!(f, "varint overflows its type"),
+            DecodeError::InvalidTag(tag) => write!(f, "the tag {} names no variant", tag),
+            DecodeError::InvalidValue(type_) => write!(f, "invalid value of type `{}`", type_),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// A type that can be encoded to bytes.
+pub trait WireEncode {
+    /// Appends the encoding of `self` to `out`.
+    fn wire_encode(&self, out: &mut Vec<u8>);
+
+    /// Returns the encoding of `self`.
+    fn to_wire(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.wire_encode(&mut out);
+        out
+    }
+}
+
+/// A type that can be decoded from bytes written by its [`WireEncode`] implementation.
+pub trait WireDecode: Sized {
+    /// Decodes a value from the start of `input`, and advances `input` past it.
+    fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+
+    /// Decodes a value that spans exactly `bytes`.
+    fn from_wire(mut bytes: &[u8]) -> Result<Self, DecodeError> {
+        let value = Self::wire_decode(&mut bytes)?;
+        if bytes.is_empty() {
+            Ok(value)
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
+
+/// Appends `value` to `out` as a LEB128 varint.
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads a LEB128 varint from the start of `input`, and advances `input` past it.
+pub fn read_varint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        *input = rest;
+        let bits = (byte & 0x7f) as u64;
+        if bits << shift >> shift != bits {
+            return Err(DecodeError::Overflow);
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::Overflow)
+}
+
+/// Takes `len` bytes from the start of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {$(
+        impl WireEncode for $t {
+            fn wire_encode(&self, out: &mut Vec<u8>) {
+                write_varint(out, *self as u64);
+            }
+        }
+
+        impl WireDecode for $t {
+            fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                read_varint(input)?.try_into().map_err(|_| DecodeError::Overflow)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed {
+    ($($t:ty),*) => {$(
+        impl WireEncode for $t {
+            fn wire_encode(&self, out: &mut Vec<u8>) {
+                let value = *self as i64;
+                write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+            }
+        }
+
+        impl WireDecode for $t {
+            fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let value = read_varint(input)?;
+                let value = (value >> 1) as i64 ^ -((value & 1) as i64);
+                value.try_into().map_err(|_| DecodeError::Overflow)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_float {
+    ($($t:ty),*) => {$(
+        impl WireEncode for $t {
+            fn wire_encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl WireDecode for $t {
+            fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let bytes = take(input, std::mem::size_of::<$t>())?;
+                Ok(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64, usize);
+impl_signed!(i8, i16, i32, i64, isize);
+impl_float!(f32, f64);
+
+impl WireEncode for bool {
+    fn wire_encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl WireDecode for bool {
+    fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match take(input, 1)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(DecodeError::InvalidValue("bool")),
+        }
+    }
+}
+
+impl WireEncode for char {
+    fn wire_encode(&self, out: &mut Vec<u8>) {
+        (*self as u32).wire_encode(out);
+    }
+}
+
+impl WireDecode for char {
+    fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        char::from_u32(u32::wire_decode(input)?).ok_or(DecodeError::InvalidValue("char"))
+    }
+}
+
+impl WireEncode for () {
+    fn wire_encode(&self, _: &mut Vec<u8>) {}
+}
+
+impl WireDecode for () {
+    fn wire_decode(_: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl WireEncode for str {
+    fn wire_encode(&self, out: &mut Vec<u8>) {
+        self.len().wire_encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl WireEncode for String {
+    fn wire_encode(&self, out: &mut Vec<u8>) {
+        self.as_str().wire_encode(out);
+    }
+}
+
+impl WireDecode for String {
+    fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = usize::wire_decode(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidValue("String"))
+    }
+}
+
+impl<T: WireEncode> WireEncode for [T] {
+    fn wire_encode(&self, out: &mut Vec<u8>) {
+        self.len().wire_encode(out);
+        for element in self {
+            element.wire_encode(out);
+        }
+    }
+}
+
+impl<T: WireEncode> WireEncode for Vec<T> {
+    fn wire_encode(&self, out: &mut Vec<u8>) {
+        self.as_slice().wire_encode(out);
+    }
+}
+
+/// The length is untrusted, so it is rejected with [`DecodeError::UnexpectedEnd`] if
+/// the rest of the input can't hold that many elements of at least one byte each.
+/// Vectors of types encoded as zero bytes, like `()`, can therefore only be
+/// decoded from input that is long enough anyway.
+///
+/// ```
+/// use try_as_traits::encoding::{DecodeError, WireDecode};
+///
+/// assert_eq!(Vec::<u8>::from_wire(&[2, 7, 8]), Ok(vec![7, 8]));
+/// assert_eq!(Vec::<u8>::from_wire(&[3, 7, 8]), Err(DecodeError::UnexpectedEnd));
+/// // Doesn't spin through `u64::MAX` empty elements.
+/// let bytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+/// assert_eq!(Vec::<()>::from_wire(&bytes), Err(DecodeError::UnexpectedEnd));
+/// ```
+impl<T: WireDecode> WireDecode for Vec<T> {
+    fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = usize::wire_decode(input)?;
+        if len > input.len() {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let mut elements = Vec::with_capacity(len);
+        for _ in 0..len {
+            elements.push(T::wire_decode(input)?);
+        }
+        Ok(elements)
+    }
+}
+
+impl<T: WireEncode + ?Sized> WireEncode for Box<T> {
+    fn wire_encode(&self, out: &mut Vec<u8>) {
+        (**self).wire_encode(out);
+    }
+}
+
+impl<T: WireDecode> WireDecode for Box<T> {
+    fn wire_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        T::wire_decode(input).map(Box::new)
+    }
+}
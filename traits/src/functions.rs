@@ -0,0 +1,44 @@
+//! Free functions over the traits of this crate, for call sites that read better
+//! with an explicit type, or to pass to higher-order functions.
+//!
+//! ```
+//! use try_as_traits::functions::{get, get_mut, holds, take};
+//!
+//! #[derive(try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::TryAsMut, try_as_macros::TypedContainer)]
+//! enum Value {
+//!     Number(i64),
+//!     String(String),
+//! }
+//! # use try_as_traits::{TryAsMut, TypedContainer};
+//!
+//! let mut value = Value::Number(1);
+//! *get_mut::<i64>(&mut value).unwrap() += 1;
+//! assert_eq!(get::<i64>(&value), Some(&2));
+//! assert!(holds::<i64>(&value));
+//!
+//! let values = vec![Value::Number(3), Value::String("three".to_owned())];
+//! let numbers: Vec<i64> = values.into_iter().filter_map(|v| take(v).ok()).collect();
+//! assert_eq!(numbers, [3]);
+//! ```
+
+use crate::{TryAsMut, TryAsRef, TypedContainer};
+
+/// Returns the value held by `value` if it is of type `T`, see [`TryAsRef`].
+pub fn get<T>(value: &impl TryAsRef<T>) -> Option<&T> {
+    value.try_as_ref()
+}
+
+/// Returns the value held by `value` mutably if it is of type `T`, see [`TryAsMut`].
+pub fn get_mut<T>(value: &mut impl TryAsMut<T>) -> Option<&mut T> {
+    value.try_as_mut()
+}
+
+/// Converts `value` into the value it holds if it is of type `T`, see [`TryInto`].
+pub fn take<T, E: TryInto<T>>(value: E) -> Result<T, E::Error> {
+    value.try_into()
+}
+
+/// Returns `true` exactly if `value` holds a value of type `T`, see [`TypedContainer::holds`].
+pub fn holds<T: 'static>(value: &impl TypedContainer) -> bool {
+    value.holds::<T>()
+}
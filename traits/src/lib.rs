@@ -2,26 +2,1778 @@
 //!
 //! See the the [crate documentation](https://nearoo.github.io/try_as/try_as/) for more information
 //! and documentation on how to use the traits.
+//!
+//! Without the default feature `std`, the crate is `no_std`. It then only contains the
+//! items that need neither `std` nor an allocator, e.g. the core traits and
+//! [`collections::ArrayTypedState`].
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::any::{type_name, Any, TypeId};
+use core::fmt::{self, Debug};
+use core::ops::{Bound, ControlFlow};
+use core::task::Poll;
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::rc::Rc;
+#[cfg(feature = "std")]
+use std::sync::{Arc, OnceLock, RwLock};
 
-use std::any::TypeId;
+#[cfg(feature = "std")]
+pub mod bus;
+#[cfg(feature = "std")]
+pub mod channel;
+pub mod collections;
+#[cfg(feature = "std")]
+pub mod convert;
+#[cfg(feature = "std")]
+pub mod deep_size;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod dispatch;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "std")]
+pub mod functions;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod migrate;
+#[cfg(feature = "std")]
+pub mod pretty;
+#[cfg(feature = "std")]
+pub mod schema;
 
 /// A version of `AsRef<T>` that can fail.
+///
+/// Besides enums, it's implemented for some standard types:
+/// * [`ControlFlow<B, C>`](ControlFlow), giving access to the `Break` value
+/// * [`Bound<T>`](Bound), giving access to the bound value unless unbounded
+/// * [`Poll<T>`](Poll), giving access to the value if ready
+/// * [`Option<T>`], giving access to the value if some
+/// * [`Result<T, E>`], giving access to the `Ok` value
+/// * boxes of and references to `dyn Any` (also `+ Send` and `+ Send + Sync`),
+///   so code generic over it accepts both closed and type-erased values (boxes only
+///   with the feature `std`):
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use std::any::Any;
+/// use try_as_traits::TryAsRef;
+///
+/// fn double(value: &impl TryAsRef<i64>) -> Option<i64> {
+///     value.try_as_ref().map(|a| a * 2)
+/// }
+///
+/// let erased: Box<dyn Any> = Box::new(21i64);
+/// assert_eq!(double(&erased), Some(42));
+/// assert_eq!(double(&(&"text" as &dyn Any)), None);
+///
+/// use try_as_traits::TypedContainer;
+/// assert!(erased.holds::<i64>());
+/// # }
+/// ```
 pub trait TryAsRef<T> {
+    #[must_use]
     fn try_as_ref(&self) -> Option<&T>;
 }
 
 /// A version of `AsMut<T>` that can fail.
 pub trait TryAsMut<T> {
+    #[must_use]
     fn try_as_mut(&mut self) -> Option<&mut T>;
 }
 
+macro_rules! impl_for_dyn_any {
+    ($($any:ty),*) => {$(
+        #[cfg(feature = "std")]
+        impl<T: 'static> TryAsRef<T> for Box<$any> {
+            fn try_as_ref(&self) -> Option<&T> {
+                (**self).downcast_ref()
+            }
+        }
+
+        #[cfg(feature = "std")]
+        impl<T: 'static> TryAsMut<T> for Box<$any> {
+            fn try_as_mut(&mut self) -> Option<&mut T> {
+                (**self).downcast_mut()
+            }
+        }
+
+        impl<T: 'static> TryAsRef<T> for &$any {
+            fn try_as_ref(&self) -> Option<&T> {
+                (**self).downcast_ref()
+            }
+        }
+
+        impl<T: 'static> TryAsRef<T> for &mut $any {
+            fn try_as_ref(&self) -> Option<&T> {
+                (**self).downcast_ref()
+            }
+        }
+
+        impl<T: 'static> TryAsMut<T> for &mut $any {
+            fn try_as_mut(&mut self) -> Option<&mut T> {
+                (**self).downcast_mut()
+            }
+        }
+
+        #[cfg(feature = "std")]
+        impl TypedContainer for Box<$any> {
+            fn type_id(&self) -> TypeId {
+                Any::type_id(&**self)
+            }
+
+            fn type_name(&self) -> &'static str {
+                type_name::<$any>()
+            }
+        }
+
+        impl TypedContainer for &$any {
+            fn type_id(&self) -> TypeId {
+                Any::type_id(&**self)
+            }
+
+            fn type_name(&self) -> &'static str {
+                type_name::<$any>()
+            }
+        }
+
+        impl TypedContainer for &mut $any {
+            fn type_id(&self) -> TypeId {
+                Any::type_id(&**self)
+            }
+
+            fn type_name(&self) -> &'static str {
+                type_name::<$any>()
+            }
+        }
+    )*};
+}
+
+impl_for_dyn_any!(dyn Any, dyn Any + Send, dyn Any + Send + Sync);
+
+// Only the `Break` value: implementing `TryAsRef<C>` as well would overlap for `B == C`.
+impl<B, C> TryAsRef<B> for ControlFlow<B, C> {
+    fn try_as_ref(&self) -> Option<&B> {
+        match self {
+            ControlFlow::Break(a) => Some(a),
+            ControlFlow::Continue(_) => None,
+        }
+    }
+}
+
+impl<B, C> TryAsMut<B> for ControlFlow<B, C> {
+    fn try_as_mut(&mut self) -> Option<&mut B> {
+        match self {
+            ControlFlow::Break(a) => Some(a),
+            ControlFlow::Continue(_) => None,
+        }
+    }
+}
+
+impl<T> TryAsRef<T> for Bound<T> {
+    fn try_as_ref(&self) -> Option<&T> {
+        match self {
+            Bound::Included(a) | Bound::Excluded(a) => Some(a),
+            Bound::Unbounded => None,
+        }
+    }
+}
+
+impl<T> TryAsMut<T> for Bound<T> {
+    fn try_as_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Bound::Included(a) | Bound::Excluded(a) => Some(a),
+            Bound::Unbounded => None,
+        }
+    }
+}
+
+impl<T> TryAsRef<T> for Poll<T> {
+    fn try_as_ref(&self) -> Option<&T> {
+        match self {
+            Poll::Ready(a) => Some(a),
+            Poll::Pending => None,
+        }
+    }
+}
+
+impl<T> TryAsMut<T> for Poll<T> {
+    fn try_as_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Poll::Ready(a) => Some(a),
+            Poll::Pending => None,
+        }
+    }
+}
+
+impl<T> TryAsRef<T> for Option<T> {
+    fn try_as_ref(&self) -> Option<&T> {
+        self.as_ref()
+    }
+}
+
+impl<T> TryAsMut<T> for Option<T> {
+    fn try_as_mut(&mut self) -> Option<&mut T> {
+        self.as_mut()
+    }
+}
+
+// Only the `Ok` value, like for `ControlFlow`.
+impl<T, E> TryAsRef<T> for Result<T, E> {
+    fn try_as_ref(&self) -> Option<&T> {
+        self.as_ref().ok()
+    }
+}
+
+impl<T, E> TryAsMut<T> for Result<T, E> {
+    fn try_as_mut(&mut self) -> Option<&mut T> {
+        self.as_mut().ok()
+    }
+}
+
+// Like their `TryAsRef` impls, `None` and `Err` hold nothing, while the type is `T` either way.
+impl<T: 'static> TypedContainer for Option<T> {
+    fn holds<U: 'static>(&self) -> bool {
+        self.is_some() && TypeId::of::<U>() == TypeId::of::<T>()
+    }
+
+    fn holds_one_of<L: TypeList>(&self) -> bool {
+        self.is_some() && L::contains(TypeId::of::<T>())
+    }
+
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+}
+
+impl<T: 'static, E> TypedContainer for Result<T, E> {
+    fn holds<U: 'static>(&self) -> bool {
+        self.is_ok() && TypeId::of::<U>() == TypeId::of::<T>()
+    }
+
+    fn holds_one_of<L: TypeList>(&self) -> bool {
+        self.is_ok() && L::contains(TypeId::of::<T>())
+    }
+
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+}
+
+/// All the conversions between an enum and one of its types `T`, as derived by the
+/// derive macros `From`, `TryInto`, `TryAsRef` and `TryAsMut`, in one bound.
+/// Implemented automatically for all types implementing these.
+///
+/// ```
+/// use try_as_traits::{TryAs, TryAsMut, TryAsRef};
+///
+/// #[derive(try_as_macros::From, try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::TryAsMut)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// fn increment<V: TryAs<i64>>(mut value: V) -> V {
+///     if let Some(number) = value.try_as_mut() {
+///         *number += 1;
+///     }
+///     value
+/// }
+///
+/// let value = increment(Value::from(1));
+/// assert_eq!(TryAsRef::<i64>::try_as_ref(&value), Some(&2));
+/// ```
+pub trait TryAs<T>: From<T> + TryInto<T> + TryAsRef<T> + TryAsMut<T> {}
+
+impl<E, T> TryAs<T> for E where E: From<T> + TryInto<T> + TryAsRef<T> + TryAsMut<T> {}
+
 /// A trait for types that can hold values of different types.
+///
+/// Besides enums, it's implemented for boxes of and references to `dyn Any`, and,
+/// matching their [`TryAsRef`] impls, for [`Option<T>`] and [`Result<T, E>`], holding
+/// a `T` if some or ok. `None` and `Err` hold nothing, so [`TypedContainer::holds`]
+/// is `false` for all types, while [`TypedContainer::type_id`] and
+/// [`TypedContainer::type_name`] still report `T`:
+/// ```
+/// use try_as_traits::TypedContainer;
+///
+/// assert!(Some(1i64).holds::<i64>());
+/// assert!(!None::<i64>.holds::<i64>());
+/// assert!(!None::<i64>.holds::<()>());
+///
+/// assert!(Ok::<u8, String>(1).holds::<u8>());
+/// let parsed = "x".parse::<u8>();
+/// assert!(!parsed.holds::<u8>());
+/// assert!(!parsed.holds::<std::num::ParseIntError>());
+/// assert!(!parsed.holds_one_of::<(u8, std::num::ParseIntError)>());
+/// ```
 pub trait TypedContainer {
     /// Returns `true` excactly if the type of the contained vlaue is `T`.
+    ///
+    /// The derive doesn't override this. With optimizations, the default already
+    /// compiles to a single comparison of the discriminant once `T` is known, just like
+    /// a `match` comparing `T` against the type of each variant would.
+    #[must_use]
     fn holds<T: 'static>(&self) -> bool {
         TypeId::of::<T>() == self.type_id()
     }
 
+    /// Returns `true` exactly if the type of the contained value is one of the types in `L`,
+    /// e.g. `holds_one_of::<(i64, f64)>()`.
+    #[must_use]
+    fn holds_one_of<L: TypeList>(&self) -> bool {
+        L::contains(self.type_id())
+    }
+
     /// Returns the [`std::any::TypeId`] of the contained value.
     fn type_id(&self) -> TypeId;
+
+    /// Returns the name of the type of the contained value, as given by [`std::any::type_name`].
+    ///
+    /// Type-erased containers like `Box<dyn Any>` can't name the erased type and
+    /// return the name of the trait object type instead, e.g. `dyn core::any::Any`.
+    ///
+    /// Defaults to the placeholder `"<unknown>"`, so that implementations written before
+    /// this method existed keep compiling. It doesn't name the contained type, so
+    /// implementations should override it, as the derive does.
+    ///
+    /// ```
+    /// use std::any::TypeId;
+    /// use try_as_traits::TypedContainer;
+    ///
+    /// struct Counter(i32);
+    ///
+    /// impl TypedContainer for Counter {
+    ///     fn type_id(&self) -> TypeId {
+    ///         TypeId::of::<i32>()
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(Counter(1).type_name(), "<unknown>");
+    /// ```
+    fn type_name(&self) -> &'static str {
+        "<unknown>"
+    }
+}
+
+/// A [`TypedContainer`] whose value can be replaced by a type-erased one.
+#[cfg(feature = "std")]
+pub trait TypedContainerMut: TypedContainer {
+    /// Replaces the contained value with `value` if the container can hold its type,
+    /// and returns the previous value. Otherwise, hands back `value`.
+    fn replace_any(&mut self, value: Box<dyn Any>) -> Result<Box<dyn Any>, Box<dyn Any>>;
+}
+
+/// A list of types, implemented for tuples of 1 to 12 `'static` types.
+pub trait TypeList {
+    /// Returns `true` exactly if one of the types in the list has the [`std::any::TypeId`] `type_id`.
+    fn contains(type_id: TypeId) -> bool;
+}
+
+macro_rules! impl_type_list {
+    ($($t:ident),*) => {
+        impl<$($t: 'static),*> TypeList for ($($t,)*) {
+            fn contains(type_id: TypeId) -> bool {
+                false $(|| type_id == TypeId::of::<$t>())*
+            }
+        }
+    };
+}
+
+impl_type_list!(A);
+impl_type_list!(A, B);
+impl_type_list!(A, B, C);
+impl_type_list!(A, B, C, D);
+impl_type_list!(A, B, C, D, E);
+impl_type_list!(A, B, C, D, E, F);
+impl_type_list!(A, B, C, D, E, F, G);
+impl_type_list!(A, B, C, D, E, F, G, H);
+impl_type_list!(A, B, C, D, E, F, G, H, I);
+impl_type_list!(A, B, C, D, E, F, G, H, I, J);
+impl_type_list!(A, B, C, D, E, F, G, H, I, J, K);
+impl_type_list!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// A trait for types that can lend out the value they hold as [`Any`].
+///
+/// Enums deriving it can opt into `#[try_as(compact)]`, which makes the derived
+/// [`TryAsRef`], [`TryAsMut`] and [`TypedContainer`] implementations downcast
+/// the result of these two methods instead of matching on the variants
+/// themselves. This shrinks the generated code of enums with many variants.
+pub trait PayloadAny {
+    /// Returns the contained value.
+    fn payload_any(&self) -> &dyn Any;
+
+    /// Returns the contained value, mutably.
+    fn payload_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A trait for comparing the value held by an enum with the value held by another enum,
+/// without converting one into the other.
+pub trait EqByInner<O: ?Sized> {
+    /// Returns `true` exactly if `other` holds a value of the same type as `self`, and
+    /// both values are equal.
+    #[must_use]
+    fn eq_inner(&self, other: &O) -> bool;
+}
+
+/// The error returned by [`TryAsRefStrict`] if the contained value has the wrong type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeMismatch {
+    /// The name of the type that was requested.
+    pub expected: &'static str,
+    /// The name of the type of the contained value.
+    pub found: &'static str,
+}
+
+impl fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a value of type `{}`, found `{}`",
+            self.expected, self.found
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TypeMismatch {}
+
+/// Versions of [`TryAsRef`] and [`TryAsMut`] that return a [`TypeMismatch`]
+/// instead of `None`.
+pub trait TryAsRefStrict {
+    /// Returns a reference to the contained value if it is of type `T`.
+    fn try_ref<T>(&self) -> Result<&T, TypeMismatch>
+    where
+        Self: TryAsRef<T> + TypedContainer,
+    {
+        self.try_as_ref().ok_or_else(|| TypeMismatch {
+            expected: type_name::<T>(),
+            found: self.type_name(),
+        })
+    }
+
+    /// Returns a mutable reference to the contained value if it is of type `T`.
+    fn try_mut<T>(&mut self) -> Result<&mut T, TypeMismatch>
+    where
+        Self: TryAsMut<T> + TypedContainer,
+    {
+        let found = self.type_name();
+        self.try_as_mut().ok_or(TypeMismatch {
+            expected: type_name::<T>(),
+            found,
+        })
+    }
+}
+
+impl<U: ?Sized> TryAsRefStrict for U {}
+
+/// Convenience methods built on [`TryAsRef`].
+pub trait TryAsRefExt {
+    /// Returns the contained value if it is of type `T`, or else `T::default()`.
+    #[cfg(feature = "std")]
+    fn as_ref_or_default<T: Default + Clone>(&self) -> Cow<'_, T>
+    where
+        Self: TryAsRef<T>,
+    {
+        match self.try_as_ref() {
+            Some(value) => Cow::Borrowed(value),
+            None => Cow::Owned(T::default()),
+        }
+    }
+
+    /// Returns a copy of the contained value if it is of type `T`, or else `T::default()`.
+    fn copied_or_default<T: Default + Copy>(&self) -> T
+    where
+        Self: TryAsRef<T>,
+    {
+        self.try_as_ref().copied().unwrap_or_default()
+    }
+
+    /// Returns the contained value if it is of type `T`, or else `fallback`.
+    fn try_as_ref_or<'a, T>(&'a self, fallback: &'a T) -> &'a T
+    where
+        Self: TryAsRef<T>,
+    {
+        self.try_as_ref().unwrap_or(fallback)
+    }
+
+    /// Applies `f` to the values of `self` and `other` if both are of type `T`.
+    fn zip_same<T, R>(&self, other: &impl TryAsRef<T>, f: impl FnOnce(&T, &T) -> R) -> Option<R>
+    where
+        Self: TryAsRef<T>,
+    {
+        Some(f(self.try_as_ref()?, other.try_as_ref()?))
+    }
+}
+
+impl<U: ?Sized> TryAsRefExt for U {}
+
+/// A version of [`TryAsRef<T>`](TryAsRef) returning a clone of the contained value.
+/// Implemented for all types implementing `TryAsRef<T>` for a `T: Clone`.
+///
+/// ```
+/// use try_as_traits::TryTo;
+///
+/// #[derive(try_as_macros::TryAsRef)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let value = Value::String("Hello".to_owned());
+/// let owned: Option<String> = value.try_to();
+/// assert_eq!(owned.as_deref(), Some("Hello"));
+/// assert_eq!(TryTo::<i64>::try_to(&value), None);
+/// ```
+pub trait TryTo<T> {
+    /// Returns a clone of the contained value if it is of type `T`.
+    #[must_use]
+    fn try_to(&self) -> Option<T>;
+}
+
+impl<U: TryAsRef<T> + ?Sized, T: Clone> TryTo<T> for U {
+    fn try_to(&self) -> Option<T> {
+        self.try_as_ref().cloned()
+    }
+}
+
+/// Convenience methods built on [`TryAsMut`].
+pub trait TryAsMutExt {
+    /// Returns the contained value if it is of type `T`. Otherwise, replaces the
+    /// contained value with `T::default()` first.
+    fn get_or_default<T: Default>(&mut self) -> &mut T
+    where
+        Self: TryAsMut<T> + From<T>,
+    {
+        if self.try_as_mut().is_none() {
+            *self = Self::from(T::default());
+        }
+        self.try_as_mut()
+            .expect("`From<T>` produced a value not holding a `T`")
+    }
+}
+
+impl<U> TryAsMutExt for U {}
+
+/// Combinators on `Option`s of borrowed enums, as returned by lookups, flattening
+/// `map.get(key).and_then(|value| value.try_as_ref())` into
+/// `map.get(key).and_as_ref()`.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use try_as_traits::{OptionTryAsExt, OptionTryIntoExt};
+///
+/// #[derive(try_as_macros::TryAsRef, try_as_macros::TryAsMut, try_as_macros::TryInto)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+/// # use try_as_traits::TryAsMut;
+///
+/// let mut config = HashMap::from([("port", Value::Number(80))]);
+/// assert_eq!(config.get("port").and_as_ref::<i64>(), Some(&80));
+/// assert_eq!(config.get("port").and_as_ref::<String>(), None);
+/// assert_eq!(config.get("host").and_as_ref::<String>(), None);
+///
+/// *config.get_mut("port").and_as_mut::<i64>().unwrap() = 443;
+/// assert_eq!(config.remove("port").and_into::<i64>(), Some(443));
+/// ```
+pub trait OptionTryAsExt<'a, E: ?Sized + 'a>: Sized {
+    /// Returns the contained value if there is one and it is of type `T`.
+    #[must_use]
+    fn and_as_ref<T>(self) -> Option<&'a T>
+    where
+        E: TryAsRef<T>;
+
+    /// Returns the contained value mutably if there is one and it is of type `T`.
+    #[must_use]
+    fn and_as_mut<T>(self) -> Option<&'a mut T>
+    where
+        E: TryAsMut<T>,
+        Self: Into<Option<&'a mut E>>,
+    {
+        self.into().and_then(TryAsMut::try_as_mut)
+    }
+}
+
+impl<'a, E: ?Sized> OptionTryAsExt<'a, E> for Option<&'a E> {
+    fn and_as_ref<T>(self) -> Option<&'a T>
+    where
+        E: TryAsRef<T>,
+    {
+        self.and_then(TryAsRef::try_as_ref)
+    }
+}
+
+impl<'a, E: ?Sized> OptionTryAsExt<'a, E> for Option<&'a mut E> {
+    fn and_as_ref<T>(self) -> Option<&'a T>
+    where
+        E: TryAsRef<T>,
+    {
+        self.and_then(|value| TryAsRef::try_as_ref(&*value))
+    }
+}
+
+/// Combinators on `Option`s of owned enums, see [`OptionTryAsExt`].
+pub trait OptionTryIntoExt<E> {
+    /// Converts the contained value into `T` if there is one and it is of type `T`.
+    #[must_use]
+    fn and_into<T>(self) -> Option<T>
+    where
+        E: TryInto<T>;
+}
+
+impl<E> OptionTryIntoExt<E> for Option<E> {
+    fn and_into<T>(self) -> Option<T>
+    where
+        E: TryInto<T>,
+    {
+        self.and_then(|value| value.try_into().ok())
+    }
+}
+
+/// A trait for tree-like enums holding lists and maps of themselves, like JSON
+/// values, to look up nested values by path.
+pub trait Navigate: Sized {
+    /// Returns the value under `key` if `self` holds a map.
+    fn child_key(&self, key: &str) -> Option<&Self>;
+
+    /// Returns the value at `index` if `self` holds a list.
+    fn child_index(&self, index: usize) -> Option<&Self>;
+
+    /// Returns the nested value at `path`, made of keys separated by `.` and of
+    /// indices in brackets, e.g. `servers[2].port`. The empty path returns `self`.
+    fn get_path(&self, path: &str) -> Option<&Self> {
+        let mut value = self;
+        let mut rest = path;
+        while !rest.is_empty() {
+            if let Some(index) = rest.strip_prefix('[') {
+                let end = index.find(']')?;
+                value = value.child_index(index[..end].parse().ok()?)?;
+                rest = &index[end + 1..];
+            } else {
+                let key = rest.strip_prefix('.').unwrap_or(rest);
+                let end = key.find(['.', '[']).unwrap_or(key.len());
+                value = value.child_key(&key[..end])?;
+                rest = &key[end..];
+            }
+        }
+        Some(value)
+    }
+
+    /// Returns the nested value at `path` if it is of type `T`, see [`Navigate::get_path`].
+    fn get_path_as<T>(&self, path: &str) -> Option<&T>
+    where
+        Self: TryAsRef<T>,
+    {
+        self.get_path(path)?.try_as_ref()
+    }
+}
+
+/// A storage keeping a dense column of values of type `T`, like the ones
+/// generated by the derive macro `Columns`.
+#[cfg(feature = "std")]
+pub trait DenseColumn<T> {
+    /// Returns the values of type `T`.
+    fn column(&self) -> &Vec<T>;
+
+    /// Returns the values of type `T` mutably.
+    fn column_mut(&mut self) -> &mut Vec<T>;
+}
+
+/// Adaptors for iterators over type enumerating enums.
+#[cfg(feature = "std")]
+pub trait TypedIteratorExt: Iterator + Sized {
+    /// Consumes the iterator, grouping its items by the [`std::any::TypeId`] of the
+    /// values they hold. The order of the items of each type is preserved.
+    fn group_by_type(self) -> HashMap<TypeId, Vec<Self::Item>>
+    where
+        Self::Item: TypedContainer,
+    {
+        let mut groups: HashMap<TypeId, Vec<Self::Item>> = HashMap::new();
+        for item in self {
+            groups
+                .entry(TypedContainer::type_id(&item))
+                .or_default()
+                .push(item);
+        }
+        groups
+    }
+
+    /// Consumes the iterator, counting its items per variant.
+    fn count_types(self) -> TypeCounts
+    where
+        Self::Item: Introspect,
+    {
+        let types = <Self::Item as Introspect>::TYPES;
+        let mut counts = vec![0; types.len()];
+        for item in self {
+            counts[item.variant_index()] += 1;
+        }
+        TypeCounts { types, counts }
+    }
+
+    /// Collects the values of type `T` held by the items, failing on the first item
+    /// holding a value of another type.
+    ///
+    /// ```
+    /// use try_as_traits::{CollectError, TypedIteratorExt};
+    ///
+    /// #[derive(try_as_macros::TryInto, try_as_macros::VariantName)]
+    /// enum Value {
+    ///     Number(i64),
+    ///     Text(String),
+    /// }
+    ///
+    /// let values = vec![Value::Text("a".to_owned()), Value::Text("b".to_owned())];
+    /// assert_eq!(values.into_iter().try_collect_as::<String>(), Ok(vec!["a".to_owned(), "b".to_owned()]));
+    ///
+    /// let values = vec![Value::Text("a".to_owned()), Value::Number(1)];
+    /// let err = values.into_iter().try_collect_as::<String>().unwrap_err();
+    /// assert_eq!((err.index, err.found), (1, "Number"));
+    /// ```
+    fn try_collect_as<T>(self) -> Result<Vec<T>, CollectError>
+    where
+        Self::Item: TryInto<T> + VariantName,
+    {
+        self.enumerate()
+            .map(|(index, item)| {
+                let found = item.variant_name();
+                item.try_into().map_err(|_| CollectError {
+                    index,
+                    expected: type_name::<T>(),
+                    found,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I: Iterator> TypedIteratorExt for I {}
+
+/// Lookups by type in slices of type enumerating enums.
+///
+/// ```
+/// use try_as_traits::TypedSliceExt;
+///
+/// #[derive(try_as_macros::TryAsRef)]
+/// enum Arg {
+///     Flag(bool),
+///     Path(String),
+/// }
+///
+/// let args = [Arg::Flag(true), Arg::Path("a.txt".to_owned()), Arg::Path("b.txt".to_owned())];
+/// assert_eq!(args.first_as::<String>().map(String::as_str), Some("a.txt"));
+/// assert_eq!(args.position_of_type::<String>(), Some(1));
+/// ```
+pub trait TypedSliceExt<E> {
+    /// Returns the first value of type `T`.
+    fn first_as<T>(&self) -> Option<&T>
+    where
+        E: TryAsRef<T>;
+
+    /// Returns the index of the first element holding a value of type `T`.
+    fn position_of_type<T>(&self) -> Option<usize>
+    where
+        E: TryAsRef<T>;
+}
+
+impl<E> TypedSliceExt<E> for [E] {
+    fn first_as<T>(&self) -> Option<&T>
+    where
+        E: TryAsRef<T>,
+    {
+        self.iter().find_map(TryAsRef::try_as_ref)
+    }
+
+    fn position_of_type<T>(&self) -> Option<usize>
+    where
+        E: TryAsRef<T>,
+    {
+        self.iter()
+            .position(|value| TryAsRef::<T>::try_as_ref(value).is_some())
+    }
+}
+
+/// A trait for enums listing the types they can hold as a tuple, derived by the
+/// derive macro `TypeSet`. See [`variant_types!`] for a shorthand.
+pub trait TypeSet {
+    /// The types of the variants, in declaration order, e.g. `(i64, String)`.
+    type Types;
+}
+
+/// Expands to the tuple of the types an enum deriving `TypeSet` can hold, e.g.
+/// `variant_types!(Value)` to `(i64, String, bool)`.
+///
+/// ```
+/// use try_as_traits::{variant_types, TypedContainer};
+///
+/// #[derive(try_as_macros::TypeSet, try_as_macros::TypedContainer)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let pair: variant_types!(Value) = (1, "one".to_owned());
+/// assert_eq!(pair.0, 1);
+/// assert!(Value::Number(1).holds_one_of::<variant_types!(Value)>());
+/// ```
+#[macro_export]
+macro_rules! variant_types {
+    ($e:ty) => {
+        <$e as $crate::TypeSet>::Types
+    };
+}
+
+/// A marker trait for enums that can hold values of type `T`, derived by the
+/// derive macro `TypeSet`.
+#[diagnostic::on_unimplemented(message = "`{Self}` can't hold values of type `{T}`")]
+pub trait ContainsType<T> {}
+
+/// Fails to compile unless the two given enums, both deriving `TypeSet`, can
+/// hold exactly the same types. Variant names and order don't matter.
+///
+/// ```
+/// # use try_as_macros::TypeSet;
+/// #[derive(TypeSet)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// #[derive(TypeSet)]
+/// enum WireValue {
+///     Text(String),
+///     Int(i64),
+/// }
+///
+/// try_as_traits::assert_same_typeset!(Value, WireValue);
+/// ```
+///
+/// ```compile_fail
+/// # use try_as_macros::TypeSet;
+/// #[derive(TypeSet)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// #[derive(TypeSet)]
+/// enum WireValue {
+///     Int(i64),
+/// }
+///
+/// try_as_traits::assert_same_typeset!(Value, WireValue);
+/// ```
+#[macro_export]
+macro_rules! assert_same_typeset {
+    ($a:ty, $b:ty $(,)?) => {
+        const _: fn() = || {
+            <$a>::__assert_types_contained_in::<$b>();
+            <$b>::__assert_types_contained_in::<$a>();
+        };
+    };
+}
+
+/// Dispatches on the types of the values held by two enums. Each arm names a type
+/// for either value and is taken if both hold values of these types, binding
+/// references to them. The arms are tried in order, and the final `_` arm is
+/// taken if none matches.
+///
+/// ```
+/// use try_as_traits::match_same;
+///
+/// #[derive(try_as_macros::TryAsRef)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// fn add(a: &Value, b: &Value) -> Option<Value> {
+///     match_same!((a, b) {
+///         (x: i64, y: i64) => Some(Value::Number(x + y)),
+///         (s: String, t: String) => Some(Value::String(format!("{}{}", s, t))),
+///         _ => None,
+///     })
+/// }
+///
+/// let sum = add(&Value::Number(1), &Value::Number(2));
+/// assert!(matches!(sum, Some(Value::Number(3))));
+/// assert!(add(&Value::Number(1), &Value::String("2".to_owned())).is_none());
+/// ```
+#[macro_export]
+macro_rules! match_same {
+    (($a:expr, $b:expr) {
+        $(($x:ident : $tx:ty, $y:ident : $ty:ty) => $body:expr,)+
+        _ => $default:expr $(,)?
+    }) => {{
+        use $crate::TryAsRef as _;
+        let (a, b) = (&$a, &$b);
+        $(
+            if let (Some($x), Some($y)) = {
+                let x: Option<&$tx> = a.try_as_ref();
+                let y: Option<&$ty> = b.try_as_ref();
+                (x, y)
+            } {
+                $body
+            } else
+        )+
+        {
+            $default
+        }
+    }};
+}
+
+/// Converts an enum into the value it holds, panicking with the location, the
+/// expected type and the found variant if it holds a value of another type.
+/// `cast!(value => T)` consumes `value` with `TryInto<T>`, `cast!(ref value => T)`
+/// borrows it with [`TryAsRef<T>`](TryAsRef). The enum must implement [`VariantName`].
+///
+/// ```
+/// use try_as_traits::cast;
+///
+/// #[derive(try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::VariantName)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let value = Value::Number(1);
+/// assert_eq!(*cast!(ref value => i64), 1);
+/// assert_eq!(cast!(value => i64), 1);
+/// ```
+///
+/// `cast!(Value::Number(2) => String)` panics with "cast failed at {file}:{line}:
+/// expected a value of type `alloc::string::String`, found variant `Number`".
+#[macro_export]
+macro_rules! cast {
+    (ref $v:expr => $t:ty) => {{
+        use $crate::TryAsRef as _;
+        let v = &$v;
+        let a: Option<&$t> = v.try_as_ref();
+        match a {
+            Some(a) => a,
+            None => $crate::__private::cast_failed::<$t>(
+                $crate::VariantName::variant_name(v),
+                file!(),
+                line!(),
+            ),
+        }
+    }};
+    ($v:expr => $t:ty) => {{
+        let v = $v;
+        let found = $crate::VariantName::variant_name(&v);
+        match ::core::convert::TryInto::<$t>::try_into(v) {
+            Ok(a) => a,
+            Err(_) => $crate::__private::cast_failed::<$t>(found, file!(), line!()),
+        }
+    }};
+}
+
+/// Consumes an enum and evaluates the arm naming the type of the value it holds,
+/// binding the value. Fails to compile unless there is an arm for every type of
+/// the enum, which must derive `TypeSet` and `TryInto`. The order of the arms
+/// doesn't matter.
+///
+/// ```
+/// use try_as_traits::handle;
+///
+/// #[derive(try_as_macros::TryInto, try_as_macros::TypeSet)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+///     Bool(bool),
+/// }
+///
+/// fn describe(value: Value) -> String {
+///     handle!(value: Value {
+///         s: String => s,
+///         n: i64 => n.to_string(),
+///         b: bool => if b { "yes" } else { "no" }.to_owned(),
+///     })
+/// }
+///
+/// assert_eq!(describe(Value::Number(3)), "3");
+/// assert_eq!(describe(Value::Bool(true)), "yes");
+/// ```
+///
+/// ```compile_fail
+/// # use try_as_traits::handle;
+/// # #[derive(try_as_macros::TryInto, try_as_macros::TypeSet)]
+/// # enum Value {
+/// #     Number(i64),
+/// #     String(String),
+/// #     Bool(bool),
+/// # }
+/// fn describe(value: Value) -> String {
+///     handle!(value: Value {
+///         s: String => s,
+///         n: i64 => n.to_string(),
+///     })
+/// }
+/// ```
+#[macro_export]
+macro_rules! handle {
+    ($v:tt : $e:ty { $($x:ident : $t:ty => $body:expr),+ $(,)? }) => {{
+        const _: fn() = || {
+            struct Handled;
+            $(impl $crate::ContainsType<$t> for Handled {})+
+            <$e>::__assert_types_contained_in::<Handled>();
+        };
+        let v: $e = $v;
+        'handle: {
+            $(
+                let v = match ::core::convert::TryInto::<$t>::try_into(v) {
+                    Ok($x) => break 'handle $body,
+                    Err(v) => v,
+                };
+            )+
+            let _ = v;
+            unreachable!("`handle!` has an arm for every type of the enum")
+        }
+    }};
+}
+
+/// A trait for enums that can list the names of the types they can hold.
+pub trait TypeNames {
+    /// The names of the types the enum can hold, as written in its definition.
+    const TYPE_NAMES: &'static [&'static str];
+
+    /// The names of the variants of the enum, in the same order as [`TypeNames::TYPE_NAMES`].
+    const VARIANT_NAMES: &'static [&'static str];
+
+    /// A hash of [`TypeNames::TYPE_NAMES`], in order. Two builds agree on it if their
+    /// enums hold the same types, written the same way, in the same order, so it can
+    /// be compared to detect incompatible versions of an enum, e.g. when connecting.
+    /// The hash is FNV-1a, and doesn't depend on the platform or compiler version.
+    ///
+    /// If the variants carry [`Tagged`] tags, the derive hashes each tag along with its
+    /// type, so that retagging a variant changes the fingerprint as well.
+    const TYPESET_FINGERPRINT: u64 = __private::fingerprint(Self::TYPE_NAMES);
+
+    /// Returns [`TypeNames::TYPE_NAMES`].
+    fn type_names() -> &'static [&'static str] {
+        Self::TYPE_NAMES
+    }
+
+    /// Returns [`TypeNames::VARIANT_NAMES`].
+    fn variant_names() -> &'static [&'static str] {
+        Self::VARIANT_NAMES
+    }
+}
+
+/// Static information about one variant of an enum implementing [`Introspect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VariantInfo {
+    /// The identifier of the variant.
+    pub name: &'static str,
+    /// The name of the variant's type, as written in the enum definition.
+    pub type_name: &'static str,
+    /// The [`std::any::TypeId`] of the variant's type.
+    pub type_id: TypeId,
+    /// The size of the variant's type in bytes.
+    pub size: usize,
+    /// The alignment of the variant's type in bytes.
+    pub align: usize,
+}
+
+/// A lookup from [`TypeId`]s to the indices they were listed at, by binary search in
+/// a sorted table, for enums with many variants where a linear search shows up.
+///
+/// [`TypeId`]s can't be ordered at compile time, so the table is built at runtime,
+/// usually once into a static, like by the derives marked with `#[try_as(type_index)]`.
+///
+/// ```
+/// use std::any::TypeId;
+/// use try_as_traits::TypeIndex;
+///
+/// let index = TypeIndex::new([TypeId::of::<i64>(), TypeId::of::<String>()]);
+/// assert_eq!(index.get(TypeId::of::<String>()), Some(1));
+/// assert_eq!(index.get(TypeId::of::<bool>()), None);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeIndex {
+    sorted: Vec<(TypeId, usize)>,
+}
+
+#[cfg(feature = "std")]
+impl TypeIndex {
+    /// Builds the lookup of `type_ids`. If a [`TypeId`] is listed several times, the
+    /// first index is kept.
+    pub fn new(type_ids: impl IntoIterator<Item = TypeId>) -> Self {
+        let mut sorted: Vec<_> = type_ids.into_iter().zip(0..).collect();
+        sorted.sort_unstable();
+        sorted.dedup_by_key(|(type_id, _)| *type_id);
+        TypeIndex { sorted }
+    }
+
+    /// Returns the index `type_id` was listed at, if any.
+    pub fn get(&self, type_id: TypeId) -> Option<usize> {
+        self.sorted
+            .binary_search_by_key(&type_id, |(type_id, _)| *type_id)
+            .ok()
+            .map(|i| self.sorted[i].1)
+    }
+}
+
+/// A trait for enums with a static table describing their variants.
+pub trait Introspect {
+    /// One entry per variant, in declaration order.
+    const TYPES: &'static [VariantInfo];
+
+    /// The number of variants, i.e. the length of [`Introspect::TYPES`], usable as the
+    /// length of arrays.
+    const VARIANT_COUNT: usize = Self::TYPES.len();
+
+    /// `true` if the enum is `#[non_exhaustive]`, so that later versions may add
+    /// variants to [`Introspect::TYPES`], i.e. the set of types is open.
+    const NON_EXHAUSTIVE: bool = false;
+
+    /// Returns the index of the variant of `self` in [`Introspect::TYPES`].
+    fn variant_index(&self) -> usize;
+
+    /// Returns the entry of the variant of `self`.
+    fn info(&self) -> &'static VariantInfo {
+        &Self::TYPES[self.variant_index()]
+    }
+
+    /// Returns the size in bytes of the type of the contained value. Unlike
+    /// [`std::mem::size_of_val`] on the enum, this doesn't include the tag or the
+    /// padding up to the largest variant.
+    fn size_of_inner(&self) -> usize {
+        self.info().size
+    }
+
+    /// Returns the alignment in bytes of the type of the contained value.
+    fn align_of_inner(&self) -> usize {
+        self.info().align
+    }
+
+    /// Returns the index in [`Introspect::TYPES`] of the variant holding values of
+    /// type `T`, if any, without needing a value.
+    fn index_of_type<T: 'static>() -> Option<usize> {
+        Self::index_of_type_id(TypeId::of::<T>())
+    }
+
+    /// Returns the index in [`Introspect::TYPES`] of the variant holding values with
+    /// the [`TypeId`] `type_id`, if any. Searches linearly, unless derived with
+    /// `#[try_as(type_index)]`, which looks it up in a [`TypeIndex`] instead.
+    fn index_of_type_id(type_id: TypeId) -> Option<usize> {
+        Self::TYPES.iter().position(|info| info.type_id == type_id)
+    }
+
+    /// Returns the entry of the variant holding values of type `T`, if any.
+    fn info_of_type<T: 'static>() -> Option<&'static VariantInfo> {
+        Self::index_of_type::<T>().map(|i| &Self::TYPES[i])
+    }
+}
+
+/// A description of the value held by an enum, returned by [`Describe::describe`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueDescription {
+    /// The identifier of the variant.
+    pub variant: &'static str,
+    /// The name of the variant's type, as written in the enum definition.
+    pub type_name: &'static str,
+    /// The `Debug` rendering of the value, if its type implements `Debug`.
+    pub value: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ValueDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.variant, self.type_name)?;
+        if let Some(value) = &self.value {
+            write!(f, " = {}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A trait for enums that can describe the value they hold, for logging and error reporting.
+#[cfg(feature = "std")]
+pub trait Describe {
+    /// Returns a description of the contained value.
+    fn describe(&self) -> ValueDescription;
+}
+
+impl<E: Introspect> Introspect for &E {
+    const TYPES: &'static [VariantInfo] = E::TYPES;
+    const NON_EXHAUSTIVE: bool = E::NON_EXHAUSTIVE;
+
+    fn variant_index(&self) -> usize {
+        (**self).variant_index()
+    }
+
+    fn index_of_type_id(type_id: TypeId) -> Option<usize> {
+        E::index_of_type_id(type_id)
+    }
+}
+
+/// A trait for enums that can build a variant, chosen at runtime, holding the default
+/// value of its type, e.g. to let users pick the type of a value before entering it.
+///
+/// ```
+/// use try_as_traits::{Instantiate, InstantiateError, Introspect};
+///
+/// struct Color(u8, u8, u8);
+///
+/// #[derive(Debug, PartialEq, try_as_macros::Introspect, try_as_macros::Instantiate)]
+/// enum Value {
+///     Number(i64),
+///     Text(String),
+///     Color(Color),
+/// }
+/// # impl PartialEq for Color { fn eq(&self, _: &Color) -> bool { true } }
+/// # impl std::fmt::Debug for Color { fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { Ok(()) } }
+///
+/// let index = Value::index_of_type::<String>().unwrap();
+/// assert_eq!(Value::instantiate(index), Ok(Value::Text(String::new())));
+/// assert_eq!(Value::instantiate(2), Err(InstantiateError::NoDefault { index: 2 }));
+/// assert_eq!(Value::instantiate(3), Err(InstantiateError::InvalidIndex { index: 3 }));
+/// ```
+pub trait Instantiate: Introspect + Sized {
+    /// Builds the variant at `index` in [`Introspect::TYPES`], holding the default
+    /// value of its type. Fails if there is no such variant, or if its type doesn't
+    /// implement [`Default`].
+    fn instantiate(index: usize) -> Result<Self, InstantiateError>;
+}
+
+/// The number of values per variant, as returned by [`TypedIteratorExt::count_types`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeCounts {
+    types: &'static [VariantInfo],
+    counts: Vec<usize>,
+}
+
+#[cfg(feature = "std")]
+impl TypeCounts {
+    /// Returns the number of values of type `T`.
+    pub fn of<T: 'static>(&self) -> usize {
+        self.types
+            .iter()
+            .position(|info| info.type_id == TypeId::of::<T>())
+            .map_or(0, |i| self.counts[i])
+    }
+
+    /// Returns the numbers of values, indexed by [`Introspect::variant_index`].
+    pub fn as_slice(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Iterates over the variants and their numbers of values, in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static VariantInfo, usize)> + '_ {
+        self.types.iter().zip(self.counts.iter().copied())
+    }
+}
+
+/// A trait for enums that can name the variant they currently hold.
+pub trait VariantName {
+    /// Returns the identifier of the variant of `self`, e.g. `"Number"` for `Value::Number(0)`.
+    fn variant_name(&self) -> &'static str;
+}
+
+/// A trait for enums whose variants carry numeric tags that are stable across
+/// reorderings and renamings of the variants, e.g. to identify them in wire formats
+/// or persisted data.
+pub trait Tagged {
+    /// The tag of each variant, in declaration order.
+    const TAGS: &'static [u16];
+
+    /// Returns the tag of the variant of `self`.
+    fn tag(&self) -> u16;
+
+    /// Returns the index of the variant with the tag `tag`, in declaration order.
+    fn variant_index_of_tag(tag: u16) -> Result<usize, InvalidTag> {
+        Self::TAGS
+            .iter()
+            .position(|t| *t == tag)
+            .ok_or(InvalidTag { tag: tag as u64 })
+    }
+}
+
+/// The error returned by [`TryIntoCtx`] if a conversion fails.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    /// The name of the type that was requested.
+    pub expected: &'static str,
+    /// The name of the variant that was found instead.
+    pub found: &'static str,
+    /// The `Debug` rendering of the value, if available.
+    pub value: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a value of type `{}`, found variant `{}`",
+            self.expected, self.found
+        )?;
+        if let Some(value) = &self.value {
+            write!(f, " ({})", value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConversionError {}
+
+/// The error returned by [`TypedIteratorExt::try_collect_as`] if an item holds a
+/// value of the wrong type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectError {
+    /// The position of the offending item in the iterator.
+    pub index: usize,
+    /// The name of the type that was requested.
+    pub expected: &'static str,
+    /// The name of the variant that was found instead.
+    pub found: &'static str,
+}
+
+impl fmt::Display for CollectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a value of type `{}` at index {}, found variant `{}`",
+            self.expected, self.index, self.found
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CollectError {}
+
+/// The error returned when converting a tagged representation of an enum, like
+/// the one generated by the derive macro `Ffi`, whose tag names no variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTag {
+    /// The offending tag.
+    pub tag: u64,
+}
+
+impl fmt::Display for InvalidTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the tag {} names no variant", self.tag)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidTag {}
+
+/// The error of [`Instantiate::instantiate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstantiateError {
+    /// The index names no variant.
+    InvalidIndex {
+        /// The offending index.
+        index: usize,
+    },
+    /// The type of the variant at the index doesn't implement [`Default`].
+    NoDefault {
+        /// The index of the variant.
+        index: usize,
+    },
+}
+
+impl fmt::Display for InstantiateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstantiateError::InvalidIndex { index } => {
+                write!(f, "the index {} names no variant", index)
+            }
+            InstantiateError::NoDefault { index } => {
+                write!(
+                    f,
+                    "the type of the variant at {} has no default value",
+                    index
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InstantiateError {}
+
+/// A version of `TryInto<T>` whose error describes what went wrong, instead of
+/// handing back the unconverted value.
+#[cfg(feature = "std")]
+pub trait TryIntoCtx: Sized {
+    /// Converts `self` into `T`, capturing the value's `Debug` rendering on failure.
+    fn try_into_ctx<T>(self) -> Result<T, ConversionError>
+    where
+        Self: TryInto<T, Error = Self> + VariantName + Debug,
+    {
+        self.try_into().map_err(|value: Self| ConversionError {
+            expected: type_name::<T>(),
+            found: value.variant_name(),
+            value: Some(format!("{:?}", value)),
+        })
+    }
+
+    /// Like [`TryIntoCtx::try_into_ctx`], for types that don't implement `Debug`.
+    fn try_into_ctx_opaque<T>(self) -> Result<T, ConversionError>
+    where
+        Self: TryInto<T, Error = Self> + VariantName,
+    {
+        self.try_into().map_err(|value: Self| ConversionError {
+            expected: type_name::<T>(),
+            found: value.variant_name(),
+            value: None,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<U> TryIntoCtx for U {}
+
+/// The error returned by [`TryUnwrapInto`], handing back the pointer.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnwrapError<P> {
+    /// The value is shared with other pointers.
+    Shared(P),
+    /// The value is of another type.
+    WrongType(P),
+}
+
+#[cfg(feature = "std")]
+impl<P> UnwrapError<P> {
+    /// Returns the pointer, regardless of why the conversion failed.
+    pub fn into_inner(self) -> P {
+        match self {
+            UnwrapError::Shared(p) | UnwrapError::WrongType(p) => p,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P> fmt::Display for UnwrapError<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnwrapError::Shared(_) => write!(f, "the value is shared"),
+            UnwrapError::WrongType(_) => write!(f, "the value is of another type"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: Debug> std::error::Error for UnwrapError<P> {}
+
+/// Consuming conversions from shared pointers to enums, like `Arc<E>` and `Rc<E>`,
+/// into the contained value. They succeed if the pointer is the only one to the
+/// enum and the enum holds a value of the requested type.
+///
+/// ```
+/// use std::sync::Arc;
+/// use try_as_traits::{TryUnwrapInto, UnwrapError};
+///
+/// #[derive(Debug, try_as_macros::TryInto)]
+/// enum Value {
+///     Number(i64),
+///     String(String),
+/// }
+///
+/// let value = Arc::new(Value::Number(1));
+/// let shared = Arc::clone(&value);
+/// let value = value.try_unwrap_into::<i64>().unwrap_err().into_inner();
+///
+/// drop(shared);
+/// let value = match value.try_unwrap_into::<String>() {
+///     Err(UnwrapError::WrongType(value)) => value,
+///     _ => unreachable!(),
+/// };
+/// assert_eq!(value.try_unwrap_into::<i64>().unwrap(), 1);
+/// ```
+#[cfg(feature = "std")]
+pub trait TryUnwrapInto: Sized {
+    /// The type of the enum behind the pointer.
+    type Inner;
+
+    /// Converts the pointer into the value held by the enum, if it is of type `T`.
+    fn try_unwrap_into<T>(self) -> Result<T, UnwrapError<Self>>
+    where
+        Self::Inner: TryInto<T, Error = Self::Inner>;
+}
+
+#[cfg(feature = "std")]
+impl<E> TryUnwrapInto for Arc<E> {
+    type Inner = E;
+
+    fn try_unwrap_into<T>(self) -> Result<T, UnwrapError<Self>>
+    where
+        E: TryInto<T, Error = E>,
+    {
+        Arc::try_unwrap(self)
+            .map_err(UnwrapError::Shared)?
+            .try_into()
+            .map_err(|value| UnwrapError::WrongType(Arc::new(value)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E> TryUnwrapInto for Rc<E> {
+    type Inner = E;
+
+    fn try_unwrap_into<T>(self) -> Result<T, UnwrapError<Self>>
+    where
+        E: TryInto<T, Error = E>,
+    {
+        Rc::try_unwrap(self)
+            .map_err(UnwrapError::Shared)?
+            .try_into()
+            .map_err(|value| UnwrapError::WrongType(Rc::new(value)))
+    }
+}
+
+/// A function wrapping a type-erased value into the enum `E`, handing the value
+/// back if `E` can't hold its type.
+#[cfg(feature = "std")]
+pub type Constructor<E> = fn(Box<dyn Any>) -> Result<E, Box<dyn Any>>;
+
+/// Describes one of the types a [`Registrable`] enum can hold.
+#[cfg(feature = "std")]
+pub struct RegistryEntry<E> {
+    /// The name of the type, as written in the enum definition.
+    pub type_name: &'static str,
+    /// The [`std::any::TypeId`] of the type.
+    pub type_id: TypeId,
+    /// Wraps a value of the type into the enum.
+    pub construct: Constructor<E>,
+}
+
+#[cfg(feature = "std")]
+impl<E> Clone for RegistryEntry<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E> Copy for RegistryEntry<E> {}
+
+#[cfg(feature = "std")]
+impl<E> Debug for RegistryEntry<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegistryEntry")
+            .field("type_name", &self.type_name)
+            .field("type_id", &self.type_id)
+            .finish()
+    }
+}
+
+/// A trait for enums whose set of types can be registered in a [`Registry`].
+#[cfg(feature = "std")]
+pub trait Registrable: Sized + 'static {
+    /// Returns one entry for each type the enum can hold, in declaration order.
+    fn registry_entries() -> Vec<RegistryEntry<Self>>;
+}
+
+/// The entries of a registered enum, with a [`TypeIndex`] of their types.
+#[cfg(feature = "std")]
+struct Registered<E> {
+    entries: Vec<RegistryEntry<E>>,
+    index: TypeIndex,
+}
+
+/// A runtime registry of the types [`Registrable`] enums can hold.
+///
+/// Registration is opt-in: either keep an explicit `Registry` around, or register
+/// into the process-wide one returned by [`Registry::global`]. Entries are looked up
+/// by [`TypeId`] in a [`TypeIndex`] built on registration.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct Registry {
+    enums: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+#[cfg(feature = "std")]
+impl Registry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the process-wide registry.
+    pub fn global() -> &'static RwLock<Registry> {
+        static GLOBAL: OnceLock<RwLock<Registry>> = OnceLock::new();
+        GLOBAL.get_or_init(Default::default)
+    }
+
+    /// Registers the types of `E`, replacing any previous registration of `E`.
+    pub fn register<E: Registrable>(&mut self) {
+        let entries = E::registry_entries();
+        let index = TypeIndex::new(entries.iter().map(|entry| entry.type_id));
+        self.enums
+            .insert(TypeId::of::<E>(), Box::new(Registered { entries, index }));
+    }
+
+    /// Returns `true` exactly if `E` has been registered.
+    pub fn is_registered<E: 'static>(&self) -> bool {
+        self.enums.contains_key(&TypeId::of::<E>())
+    }
+
+    /// Returns the entries of all types `E` can hold, if `E` has been registered.
+    pub fn entries<E: 'static>(&self) -> Option<&[RegistryEntry<E>]> {
+        self.registered::<E>()
+            .map(|registered| registered.entries.as_slice())
+    }
+
+    /// Returns the registration of `E`, if any.
+    fn registered<E: 'static>(&self) -> Option<&Registered<E>> {
+        self.enums
+            .get(&TypeId::of::<E>())
+            .and_then(|registered| registered.downcast_ref())
+    }
+
+    /// Looks up the entry of the type of `E` named `type_name`.
+    pub fn entry_by_name<E: 'static>(&self, type_name: &str) -> Option<&RegistryEntry<E>> {
+        self.entries::<E>()?
+            .iter()
+            .find(|entry| entry.type_name == type_name)
+    }
+
+    /// Looks up the entry of the type of `E` with the given [`std::any::TypeId`].
+    pub fn entry_by_type_id<E: 'static>(&self, type_id: TypeId) -> Option<&RegistryEntry<E>> {
+        let registered = self.registered::<E>()?;
+        registered
+            .index
+            .get(type_id)
+            .map(|i| &registered.entries[i])
+    }
+
+    /// Wraps `value` into `E`, if `E` is registered and can hold the type of `value`.
+    /// Otherwise, `value` is handed back.
+    pub fn construct<E: 'static>(&self, value: Box<dyn Any>) -> Result<E, Box<dyn Any>> {
+        match self.entry_by_type_id::<E>((*value).type_id()) {
+            Some(entry) => (entry.construct)(value),
+            None => Err(value),
+        }
+    }
+}
+
+/// Implementation details of the derive macros. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "std")]
+    use std::fmt::Debug;
+
+    /// Renders a value with `Debug`, if its type implements it. Must be called as
+    /// `(&DebugIfAvailable(&value)).render()` on a concrete type, so that method
+    /// resolution prefers [`RenderDebug`] over [`RenderNothing`].
+    #[cfg(feature = "std")]
+    pub struct DebugIfAvailable<'a, T>(pub &'a T);
+
+    #[cfg(feature = "std")]
+    pub trait RenderDebug {
+        fn render(&self) -> Option<String>;
+    }
+
+    #[cfg(feature = "std")]
+    impl<T: Debug> RenderDebug for DebugIfAvailable<'_, T> {
+        fn render(&self) -> Option<String> {
+            Some(format!("{:?}", self.0))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub trait RenderNothing {
+        fn render(&self) -> Option<String>;
+    }
+
+    #[cfg(feature = "std")]
+    impl<T> RenderNothing for &DebugIfAvailable<'_, T> {
+        fn render(&self) -> Option<String> {
+            None
+        }
+    }
+
+    /// Builds the default value of `T`, if it implements `Default`. Must be called as
+    /// `(&DefaultIfAvailable::<T>(PhantomData)).make()` on a concrete type, like
+    /// [`DebugIfAvailable`].
+    pub struct DefaultIfAvailable<T>(pub core::marker::PhantomData<T>);
+
+    pub trait MakeDefault<T> {
+        fn make(&self) -> Option<T>;
+    }
+
+    impl<T: Default> MakeDefault<T> for DefaultIfAvailable<T> {
+        fn make(&self) -> Option<T> {
+            Some(T::default())
+        }
+    }
+
+    pub trait MakeNothing<T> {
+        fn make(&self) -> Option<T>;
+    }
+
+    impl<T> MakeNothing<T> for &DefaultIfAvailable<T> {
+        fn make(&self) -> Option<T> {
+            None
+        }
+    }
+
+    /// The FNV-1a hash of `names`, each followed by a `0xff` byte, which can't occur
+    /// in UTF-8, to separate them.
+    pub const fn fingerprint(names: &[&str]) -> u64 {
+        fingerprint_tagged(names, &[])
+    }
+
+    /// Like [`fingerprint`], with the little-endian bytes of `tags[i]` hashed after the
+    /// separator of `names[i]`, for as many tags as there are.
+    pub const fn fingerprint_tagged(names: &[&str], tags: &[u16]) -> u64 {
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut i = 0;
+        while i < names.len() {
+            let bytes = names[i].as_bytes();
+            let mut j = 0;
+            while j < bytes.len() {
+                hash = (hash ^ bytes[j] as u64).wrapping_mul(PRIME);
+                j += 1;
+            }
+            hash = (hash ^ 0xff).wrapping_mul(PRIME);
+            if i < tags.len() {
+                let [low, high] = tags[i].to_le_bytes();
+                hash = (hash ^ low as u64).wrapping_mul(PRIME);
+                hash = (hash ^ high as u64).wrapping_mul(PRIME);
+            }
+            i += 1;
+        }
+        hash
+    }
+
+    /// The panic of `cast!`.
+    #[cold]
+    #[track_caller]
+    pub fn cast_failed<T>(found: &str, file: &str, line: u32) -> ! {
+        panic!(
+            "cast failed at {}:{}: expected a value of type `{}`, found variant `{}`",
+            file,
+            line,
+            core::any::type_name::<T>(),
+            found
+        )
+    }
 }
@@ -0,0 +1,49 @@
+//! Deep merging of nested enums, e.g. to layer configuration, see [`Merge`].
+
+use std::collections::BTreeMap;
+
+/// How [`Merge::merge`] combines two lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The entries of the overriding list are appended to the entries of the other one.
+    ConcatLists,
+    /// The overriding list replaces the other one.
+    ReplaceLists,
+}
+
+/// A trait for enums holding lists and maps of themselves that can be merged deeply.
+pub trait Merge: Sized {
+    /// Merges `other` into `self`, with `other` taking precedence. Maps are merged key
+    /// by key, recursively, lists according to `strategy`, and all other values, as
+    /// well as values held by different variants, are replaced by `other`.
+    #[must_use]
+    fn merge(self, other: Self, strategy: MergeStrategy) -> Self;
+}
+
+/// Merges two lists according to `strategy`, see [`Merge::merge`].
+pub fn merge_lists<E>(mut base: Vec<E>, other: Vec<E>, strategy: MergeStrategy) -> Vec<E> {
+    match strategy {
+        MergeStrategy::ConcatLists => {
+            base.extend(other);
+            base
+        }
+        MergeStrategy::ReplaceLists => other,
+    }
+}
+
+/// Merges two maps key by key, merging the values of keys in both, see [`Merge::merge`].
+pub fn merge_maps<E, M>(base: M, other: M, strategy: MergeStrategy) -> M
+where
+    E: Merge,
+    M: IntoIterator<Item = (String, E)> + FromIterator<(String, E)>,
+{
+    let mut merged: BTreeMap<String, E> = base.into_iter().collect();
+    for (key, value) in other {
+        let value = match merged.remove(&key) {
+            Some(base) => base.merge(value, strategy),
+            None => value,
+        };
+        merged.insert(key, value);
+    }
+    merged.into_iter().collect()
+}
@@ -0,0 +1,102 @@
+//! Converting values between two generations of an enum, see [`Migration`].
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::{Registrable, RegistryEntry};
+
+/// A hook of a [`Migration`] with its argument type erased.
+type Hook<B> = Box<dyn Fn(Box<dyn Any>) -> B>;
+
+/// Converts values of the enum `A` into values of the enum `B`.
+///
+/// Values of types both enums can hold are moved into the variant of `B` holding
+/// that type, whatever the variants are named. Values of other types, and values
+/// that should be converted differently, are passed to hooks registered per type.
+/// `A` must derive `IntoBoxedAny`, and `B` must derive `Registrable`.
+///
+/// ```
+/// use try_as_traits::migrate::Migration;
+///
+/// #[derive(try_as_macros::IntoBoxedAny)]
+/// enum ValueV1 {
+///     Number(i64),
+///     Text(String),
+///     Flag(bool),
+/// }
+///
+/// #[derive(Debug, PartialEq, try_as_macros::Registrable)]
+/// enum ValueV2 {
+///     Number(i64),
+///     String(String),
+///     Count(u32),
+/// }
+///
+/// let mut migration = Migration::<ValueV1, ValueV2>::new();
+/// migration.hook(|flag: bool| ValueV2::Count(flag as u32));
+///
+/// let text = ValueV1::Text("hi".to_owned());
+/// assert_eq!(migration.migrate(text).unwrap(), ValueV2::String("hi".to_owned()));
+/// assert_eq!(migration.migrate(ValueV1::Flag(true)).unwrap(), ValueV2::Count(1));
+/// ```
+pub struct Migration<A, B> {
+    hooks: HashMap<TypeId, Hook<B>>,
+    entries: Vec<RegistryEntry<B>>,
+    from: PhantomData<fn(A)>,
+}
+
+impl<A, B> Migration<A, B>
+where
+    A: Into<Box<dyn Any + Send + Sync>>,
+    B: Registrable,
+{
+    /// Creates a migration without hooks.
+    pub fn new() -> Self {
+        Migration {
+            hooks: HashMap::new(),
+            entries: B::registry_entries(),
+            from: PhantomData,
+        }
+    }
+
+    /// Registers `hook` to convert values of type `T`, replacing any previous hook of `T`.
+    /// Hooks take precedence over moving values into the variant holding their type.
+    pub fn hook<T: 'static>(&mut self, hook: impl Fn(T) -> B + 'static) -> &mut Self {
+        self.hooks.insert(
+            TypeId::of::<T>(),
+            Box::new(move |value| {
+                hook(
+                    *value
+                        .downcast()
+                        .expect("hooks are keyed by the type they convert"),
+                )
+            }),
+        );
+        self
+    }
+
+    /// Converts `value`, handing back the value it holds if `B` can't hold its type
+    /// and there is no hook for it.
+    pub fn migrate(&self, value: A) -> Result<B, Box<dyn Any>> {
+        let value: Box<dyn Any> = value.into();
+        let type_id = (*value).type_id();
+        if let Some(hook) = self.hooks.get(&type_id) {
+            return Ok(hook(value));
+        }
+        match self.entries.iter().find(|entry| entry.type_id == type_id) {
+            Some(entry) => (entry.construct)(value),
+            None => Err(value),
+        }
+    }
+}
+
+impl<A, B> Default for Migration<A, B>
+where
+    A: Into<Box<dyn Any + Send + Sync>>,
+    B: Registrable,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
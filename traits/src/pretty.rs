@@ -0,0 +1,83 @@
+//! Rendering nested enums as indented trees, see [`PrettyPrint`].
+
+use std::fmt::{self, Debug, Write};
+
+/// Options of [`PrettyPrint::pretty_print`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// The number of levels rendered below the root. The values held by deeper lists
+    /// and maps are elided.
+    pub max_depth: usize,
+    /// The number of characters the rendering of a value is truncated to.
+    pub max_width: usize,
+    /// The number of spaces indenting each level.
+    pub indent: usize,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        PrettyOptions {
+            max_depth: usize::MAX,
+            max_width: 80,
+            indent: 2,
+        }
+    }
+}
+
+/// A trait for enums holding lists and maps of themselves, rendering them as indented
+/// trees with one value per line, annotated with the variant and type.
+pub trait PrettyPrint {
+    /// Writes the rendering of `self`, nested `depth` levels below the root, to `out`.
+    fn write_pretty(
+        &self,
+        out: &mut dyn Write,
+        options: &PrettyOptions,
+        depth: usize,
+    ) -> fmt::Result;
+
+    /// Returns the rendering of `self`.
+    fn pretty_print(&self, options: &PrettyOptions) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, options, 0)
+            .expect("writing to a String doesn't fail");
+        out
+    }
+}
+
+/// Writes a value that holds no nested values, see [`PrettyPrint::write_pretty`].
+pub fn write_leaf(
+    out: &mut dyn Write,
+    variant: &str,
+    type_name: &str,
+    value: &dyn Debug,
+    options: &PrettyOptions,
+) -> fmt::Result {
+    let value = format!("{:?}", value);
+    write!(out, "{}: {} = ", variant, type_name)?;
+    match value.char_indices().nth(options.max_width) {
+        Some((end, _)) => write!(out, "{}…", &value[..end]),
+        None => out.write_str(&value),
+    }
+}
+
+/// Writes a value holding `children`, each labeled with its index or key, see
+/// [`PrettyPrint::write_pretty`].
+pub fn write_children<'a, E: PrettyPrint + 'a>(
+    out: &mut dyn Write,
+    variant: &str,
+    type_name: &str,
+    children: impl ExactSizeIterator<Item = (String, &'a E)>,
+    options: &PrettyOptions,
+    depth: usize,
+) -> fmt::Result {
+    write!(out, "{}: {} ({})", variant, type_name, children.len())?;
+    if children.len() > 0 && depth >= options.max_depth {
+        return out.write_str(" …");
+    }
+    for (label, child) in children {
+        let indent = (depth + 1) * options.indent;
+        write!(out, "\n{:indent$}{}: ", "", label, indent = indent)?;
+        child.write_pretty(out, options, depth + 1)?;
+    }
+    Ok(())
+}
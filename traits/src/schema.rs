@@ -0,0 +1,405 @@
+//! A machine-readable description of the type set of an enum, see [`TypeSetSchema`].
+//!
+//! Schemas are exchanged as small JSON documents of the form
+//! ```json
+//! {"name":"Value","variants":[{"name":"Number","type":"i64","tag":1}]}
+//! ```
+//! and can be compared with [`TypeSetSchema::diff`] to find out whether two builds
+//! agree on an enum. Variants are identified by their [`Tagged`] tag, so that
+//! reordering or renaming them keeps schemas compatible:
+//! ```
+//! use try_as_traits::schema::TypeSetSchema;
+//!
+//! let old = TypeSetSchema::from_json(
+//!     r#"{"name":"Value","variants":[{"name":"Number","type":"i64","tag":1}]}"#,
+//! ).unwrap();
+//! let mut new = old.clone();
+//! new.variants[0].type_name = "u64".to_owned();
+//!
+//! assert_eq!(TypeSetSchema::from_json(&new.to_json()).unwrap(), new);
+//! assert!(!old.diff(&new).is_compatible());
+//! ```
+
+use std::fmt::{self, Write};
+
+use crate::{Introspect, Tagged};
+
+/// One variant of a [`TypeSetSchema`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SchemaVariant {
+    /// The identifier of the variant.
+    pub name: String,
+    /// The name of the variant's type, as written in the enum definition.
+    pub type_name: String,
+    /// The tag of the variant, see [`Tagged`].
+    pub tag: u16,
+}
+
+/// The type set of an enum: its name and its variants.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeSetSchema {
+    /// The name of the enum.
+    pub name: String,
+    /// The variants of the enum, in declaration order.
+    pub variants: Vec<SchemaVariant>,
+}
+
+/// The differences between two [`TypeSetSchema`]s, as returned by [`TypeSetSchema::diff`].
+/// Variants are matched by tag.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Variants only present in the newer schema.
+    pub added: Vec<SchemaVariant>,
+    /// Variants only present in the older schema.
+    pub removed: Vec<SchemaVariant>,
+    /// Variants present in both schemas whose name or type differ, as `(old, new)`.
+    pub changed: Vec<(SchemaVariant, SchemaVariant)>,
+}
+
+impl SchemaDiff {
+    /// Returns `true` exactly if the schemas are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns `true` exactly if every value of the older schema can be read with the
+    /// newer one, i.e. if variants were at most added or renamed.
+    pub fn is_compatible(&self) -> bool {
+        self.removed.is_empty()
+            && self
+                .changed
+                .iter()
+                .all(|(old, new)| old.type_name == new.type_name)
+    }
+}
+
+/// The error returned by [`TypeSetSchema::from_json`] if a document can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// The byte offset at which parsing failed.
+    pub position: usize,
+    /// What was expected at that position.
+    pub message: &'static str,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid schema at byte {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl TypeSetSchema {
+    /// Returns the schema of `E`, named `name`.
+    ///
+    /// ```
+    /// use try_as_traits::schema::TypeSetSchema;
+    ///
+    /// #[derive(try_as_macros::Introspect, try_as_macros::Tagged)]
+    /// enum Old {
+    ///     #[try_as(tag = 1)]
+    ///     Number(i64),
+    ///     #[try_as(tag = 2)]
+    ///     Text(String),
+    /// }
+    ///
+    /// #[derive(try_as_macros::Introspect, try_as_macros::Tagged)]
+    /// enum New {
+    ///     #[try_as(tag = 2)]
+    ///     Label(String),
+    ///     #[try_as(tag = 1)]
+    ///     Number(i64),
+    /// }
+    ///
+    /// let old = TypeSetSchema::of::<Old>("Value");
+    /// let new = TypeSetSchema::of::<New>("Value");
+    /// assert!(old.diff(&new).is_compatible());
+    /// ```
+    pub fn of<E: Introspect + Tagged>(name: &str) -> Self {
+        TypeSetSchema {
+            name: name.to_owned(),
+            variants: E::TYPES
+                .iter()
+                .zip(E::TAGS)
+                .map(|(info, &tag)| SchemaVariant {
+                    name: info.name.to_owned(),
+                    type_name: info.type_name.to_owned(),
+                    tag,
+                })
+                .collect(),
+        }
+    }
+
+    /// Serializes the schema to a JSON document.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"name\":");
+        write_json_string(&mut json, &self.name);
+        json.push_str(",\"variants\":[");
+        for (i, variant) in self.variants.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"name\":");
+            write_json_string(&mut json, &variant.name);
+            json.push_str(",\"type\":");
+            write_json_string(&mut json, &variant.type_name);
+            write!(json, ",\"tag\":{}}}", variant.tag).unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// Parses a JSON document produced by [`TypeSetSchema::to_json`]. Unknown keys are ignored.
+    ///
+    /// The parser is hand-rolled and only meant for such documents, not for JSON in
+    /// general:
+    /// * numbers are only accepted as tags, and only as integers without sign,
+    ///   fraction or exponent
+    /// * values of unknown keys are skipped without being validated, e.g. `tru` is
+    ///   accepted in place of `true`
+    /// * duplicate keys aren't rejected, the last one wins
+    /// * `\u` escapes of surrogate pairs aren't combined and fail to parse
+    /// * nested values of unknown keys are skipped recursively, so deeply nested
+    ///   documents can overflow the stack
+    pub fn from_json(json: &str) -> Result<Self, SchemaError> {
+        let mut parser = Parser { json, position: 0 };
+        let schema = parser.schema()?;
+        parser.skip_whitespace();
+        if parser.position != json.len() {
+            return Err(parser.error("end of document"));
+        }
+        Ok(schema)
+    }
+
+    /// Compares `self`, the older schema, with `newer`.
+    pub fn diff(&self, newer: &TypeSetSchema) -> SchemaDiff {
+        let mut diff = SchemaDiff::default();
+        for old in &self.variants {
+            match newer.variants.iter().find(|new| new.tag == old.tag) {
+                Some(new) if new != old => diff.changed.push((old.clone(), new.clone())),
+                Some(_) => {}
+                None => diff.removed.push(old.clone()),
+            }
+        }
+        for new in &newer.variants {
+            if !self.variants.iter().any(|old| old.tag == new.tag) {
+                diff.added.push(new.clone());
+            }
+        }
+        diff
+    }
+}
+
+fn write_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            '\r' => json.push_str("\\r"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// A minimal parser for the subset of JSON used by schema documents.
+struct Parser<'a> {
+    json: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> SchemaError {
+        SchemaError {
+            position: self.position,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.json[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.json[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, c: char, message: &'static str) -> Result<(), SchemaError> {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    /// Parses the entries of an object, calling `entry` with each key.
+    fn object(
+        &mut self,
+        mut entry: impl FnMut(&mut Self, String) -> Result<(), SchemaError>,
+    ) -> Result<(), SchemaError> {
+        self.expect('{', "`{`")?;
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(());
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(':', "`:`")?;
+            entry(self, key)?;
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("`,` or `}`")),
+            }
+        }
+    }
+
+    fn schema(&mut self) -> Result<TypeSetSchema, SchemaError> {
+        let mut name = None;
+        let mut variants = None;
+        self.object(|parser, key| {
+            match key.as_str() {
+                "name" => name = Some(parser.string()?),
+                "variants" => variants = Some(parser.variants()?),
+                _ => parser.skip_value()?,
+            }
+            Ok(())
+        })?;
+        Ok(TypeSetSchema {
+            name: name.ok_or_else(|| self.error("key `name`"))?,
+            variants: variants.ok_or_else(|| self.error("key `variants`"))?,
+        })
+    }
+
+    fn variants(&mut self) -> Result<Vec<SchemaVariant>, SchemaError> {
+        let mut variants = Vec::new();
+        self.array(|parser| {
+            variants.push(parser.variant()?);
+            Ok(())
+        })?;
+        Ok(variants)
+    }
+
+    fn variant(&mut self) -> Result<SchemaVariant, SchemaError> {
+        let mut name = None;
+        let mut type_name = None;
+        let mut tag = None;
+        self.object(|parser, key| {
+            match key.as_str() {
+                "name" => name = Some(parser.string()?),
+                "type" => type_name = Some(parser.string()?),
+                "tag" => tag = Some(parser.number()?),
+                _ => parser.skip_value()?,
+            }
+            Ok(())
+        })?;
+        Ok(SchemaVariant {
+            name: name.ok_or_else(|| self.error("key `name`"))?,
+            type_name: type_name.ok_or_else(|| self.error("key `type`"))?,
+            tag: tag.ok_or_else(|| self.error("key `tag`"))?,
+        })
+    }
+
+    /// Parses the elements of an array, calling `element` for each.
+    fn array(
+        &mut self,
+        mut element: impl FnMut(&mut Self) -> Result<(), SchemaError>,
+    ) -> Result<(), SchemaError> {
+        self.expect('[', "`[`")?;
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(());
+        }
+        loop {
+            element(self)?;
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("`,` or `]`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, SchemaError> {
+        self.expect('"', "a string")?;
+        let mut s = String::new();
+        let mut chars = self.json[self.position..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += i + 1;
+                    return Ok(s);
+                }
+                '\\' => match chars.next() {
+                    Some((_, '"')) => s.push('"'),
+                    Some((_, '\\')) => s.push('\\'),
+                    Some((_, '/')) => s.push('/'),
+                    Some((_, 'n')) => s.push('\n'),
+                    Some((_, 't')) => s.push('\t'),
+                    Some((_, 'r')) => s.push('\r'),
+                    Some((_, 'u')) => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error("a valid unicode escape"))?;
+                        s.push(c);
+                    }
+                    _ => return Err(self.error("a valid escape sequence")),
+                },
+                c => s.push(c),
+            }
+        }
+        Err(self.error("the end of a string"))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, SchemaError> {
+        self.skip_whitespace();
+        let rest = &self.json[self.position..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number = rest[..len]
+            .parse()
+            .map_err(|_| self.error("a non-negative integer"))?;
+        self.position += len;
+        Ok(number)
+    }
+
+    fn skip_value(&mut self) -> Result<(), SchemaError> {
+        match self.peek() {
+            Some('"') => self.string().map(drop),
+            Some('{') => self.object(|parser, _| parser.skip_value()),
+            Some('[') => self.array(Self::skip_value),
+            _ => {
+                let rest = &self.json[self.position..];
+                let len = rest
+                    .find(|c: char| c == ',' || c == '}' || c == ']' || c.is_whitespace())
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    return Err(self.error("a value"));
+                }
+                self.position += len;
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use try_as_traits::cast;
+
+#[derive(try_as_macros::TryInto, try_as_macros::TryAsRef, try_as_macros::VariantName)]
+enum Value {
+    Number(i64),
+    String(String),
+}
+
+#[test]
+fn casts_to_the_held_type() {
+    let value = Value::String("a".to_owned());
+    assert_eq!(cast!(ref value => String), "a");
+    assert_eq!(cast!(value => String), "a");
+}
+
+#[test]
+// The type is rendered by `type_name`, whose output isn't stable.
+#[should_panic(expected = "found variant `Number`")]
+fn panics_on_another_type() {
+    cast!(Value::Number(2) => String);
+}
+
+#[test]
+#[should_panic(expected = "tests/cast.rs:")]
+fn panics_at_the_call_site() {
+    let value = Value::Number(2);
+    cast!(ref value => String);
+}
@@ -5,6 +5,51 @@
 //! * [`traits::TryAsRef`] - like `AsRef<T>`, but allowed to fail
 //! * [`traits::TryAsMut`] - like `AsMut<T>`, but allowed to fail
 //! * [`traits::TypedContainer`] - inspect types of a container
+//! * [`traits::TypeNames`] - list the types an enum can hold
+//! * [`traits::VariantName`] - name the variant of an enum
+//! * [`traits::TryIntoCtx`] - like `TryInto<T>`, but with a descriptive error
+//! * [`traits::Registry`] - look up the types of enums at runtime
+//! * [`traits::TryAsRefStrict`] - like `TryAsRef`/`TryAsMut`, but returning a `TypeMismatch` error
+//! * [`traits::TryAsRefExt`] - convenience methods built on `TryAsRef`
+//! * [`traits::TryAsMutExt`] - convenience methods built on `TryAsMut`
+//! * [`traits::Introspect`] - a static table describing the variants of an enum
+//! * [`traits::Instantiate`] - building a variant, chosen by index, from the default value of its type
+//! * [`traits::TypeIndex`] - look up the index of a `TypeId` by binary search, for enums with many variants
+//! * [`traits::Describe`] - describe the value of an enum for logging
+//! * [`traits::schema`] - export and compare the type sets of enums as JSON
+//! * [`traits::collections::TypedMultiMap`] - store many values of an enum, grouped by type
+//! * [`traits::collections::TypedState`] - keep the latest value of each type of an enum, notifying subscribers of changes
+//! * [`traits::collections::ConcurrentTypedState`] - keep the latest value of each type of an enum, shared between threads
+//! * [`traits::collections::ArrayTypedState`] - keep the latest value of each type of an enum in an array, without allocating
+//! * [`traits::DenseColumn`] - a storage with a dense column of values of one type
+//! * [`traits::TypedIteratorExt`] - adaptors for iterators over enums
+//! * [`traits::ContainsType`] - mark the types an enum can hold, and compare them with `assert_same_typeset!`
+//! * [`traits::PayloadAny`] - lend out the contained value as `dyn Any`
+//! * [`traits::match_same!`] - dispatch on the types of the values held by two enums
+//! * [`traits::variant_types!`] - name the tuple of the types an enum can hold
+//! * [`traits::cast!`] - convert an enum into the value it holds, or panic
+//! * [`traits::Navigate`] - look up nested values of tree-like enums by path
+//! * [`traits::OptionTryAsExt`] - access the values of enums in `Option`s, e.g. after lookups
+//! * [`traits::TryAs`] - all conversions between an enum and one of its types in one bound
+//! * [`traits::functions`] - free functions `get`, `get_mut`, `take` and `holds`, also re-exported at the crate root
+//! * [`traits::TypedSliceExt`] - lookups by type in slices of enums
+//! * [`traits::TryUnwrapInto`] - convert shared pointers to enums into the contained value
+//! * [`traits::TryTo`] - like `TryAsRef`, but returning a clone of the value
+//! * [`traits::TypedContainerMut`] - replace the value of a container with a type-erased one
+//! * [`traits::dispatch`] - route the values of enums to handlers registered per type
+//! * [`traits::dispatch::TypedBus`] - a synchronous event bus routing values of enums by the type they hold
+//! * [`traits::bus::AsyncTypedBus`] - an asynchronous event bus for enums, with a subscription per type
+//! * [`traits::channel`] - channels of enums, with receiving by the type of the values
+//! * [`traits::handle`] - evaluate the arm naming the type of the value of an enum, checking that all types are handled
+//! * [`traits::Tagged`] - give the variants of enums stable numeric tags
+//! * [`traits::encoding`] - encode enums compactly as the tag of the variant followed by the value
+//! * [`traits::migrate::Migration`] - convert values between two generations of an enum
+//! * [`traits::convert::Conversions`] - convert values along the shortest chain of registered conversions, and [`traits::convert::ToVariant`] to convert the value held by an enum that way
+//! * [`traits::EqByInner`] - compare the values held by two different enums
+//! * [`traits::pretty::PrettyPrint`] - render nested enums as indented trees annotated with variants and types
+//! * [`traits::diff::Diff`] - compare nested enums structurally, recursing into lists and maps
+//! * [`traits::merge::Merge`] - merge nested enums deeply, e.g. to layer configuration
+//! * [`traits::deep_size::DeepSize`] - estimate the memory used by values, including their heap allocations
 //!
 //! And a set of macros that derive implementations from these and some
 //! standard traits, namely:
@@ -13,11 +58,56 @@
 //! * [`macros::TryAsMut`] to get references of the values of the enum
 //! * [`macros::TryAsRef`] to get mutable references of the values of the enum
 //! * [`macros::TypedContainer`] to inspect the type in the enum
+//! * [`macros::TypeNames`] to list the types the enum can hold
+//! * [`macros::VariantName`] to name the variant of the enum
+//! * [`macros::Registrable`] to register the types of the enum in a registry
+//! * [`macros::Constructors`] to build the enum from type-erased values
+//! * [`macros::IntoParts`] to destructure the enum into a tuple of `Option`s
+//! * [`macros::IntoOption`] to convert the enum into an `Option` of one of its types
+//! * [`macros::TryFromAnyRef`] to build the enum from a borrowed type-erased value
+//! * [`macros::IntoBoxedAny`] to convert the enum into a boxed, type-erased value
+//! * [`macros::AsDyn`] to view or convert the value of the enum as a trait object
+//! * [`macros::Introspect`] to describe the variants of the enum in a static table
+//! * [`macros::Instantiate`] to build variants chosen by index from default values
+//! * [`macros::Describe`] to describe the value of the enum
+//! * [`macros::Columns`] to store values of the enum in dense per-type columns
+//! * [`macros::TypeSet`] to mark the types the enum can hold
+//! * [`macros::PayloadAny`] to derive `PayloadAny`, the shared helper behind `#[try_as(compact)]`
+//! * [`macros::Ffi`] to pass the enum across a C boundary as a `#[repr(C)]` tagged union
+//! * [`macros::Default`] to build the enum from the default value of a marked variant
+//! * [`macros::Navigate`] to look up nested values by path
+//! * [`macros::IntoBoxedError`] to convert the enum into a boxed error and back
+//! * [`macros::ErrorEnum`] to use the enum as an error forwarding to the contained error
+//! * [`macros::TypedContainerMut`] to replace the value of the enum with a type-erased one
+//! * [`macros::SampleOfEach`] to list one value of each variant, e.g. for tests
+//! * [`macros::Hash`] to hash the enum by its variant, identified by index, name or type, and its value
+//! * [`macros::Tagged`] to tag the variants with numbers given by attributes
+//! * [`macros::WireEncode`] to encode the enum as its tag followed by the value
+//! * [`macros::WireDecode`] to decode the enum from its tag followed by the value
+//! * [`macros::TryFromRef`] to clone the contained value out of a reference to the enum
+//! * [`macros::TryNew`] to wrap a value of any type into the enum, if it can hold it
+//! * [`macros::Index`] to index into the lists and maps of the enum, like `serde_json::Value`
+//! * [`macros::EqByInner`] to derive `EqByInner` against every enum implementing `PayloadAny`
+//! * [`macros::Discriminant`] to get the declared discriminants of an enum with a primitive representation
+//! * [`macros::PrettyPrint`] to derive `PrettyPrint`, recursing into lists and maps of the enum
+//! * [`macros::Diff`] to derive `Diff`, recursing into lists and maps of the enum
+//! * [`macros::Merge`] to derive `Merge`, recursing into lists and maps of the enum
+//! * [`macros::DeepSize`] to derive `DeepSize`, recursing into nested values of the enum
 //!
 //! To derive the traits for an enum, the enum has to have the following shape:
 //! * Each variant must have exactly one unnamed parameter
 //! * Each variant argument type must appear at most once
 //!
+//! `From`, `TryAsRef` and `TryAsMut` also accept tuple structs with a single field
+//! if they are marked with `#[try_as(transparent)]`.
+//!
+//! `From`, `TryInto`, `TryAsRef`, `TryAsMut` and `TypedContainer` also accept variants of
+//! other shapes if they are marked with `#[try_as(with = "module")]`, converting them
+//! with the functions of the module.
+//!
+//! With `#[try_as(debug_expand)]` on the enum, each derive also generates a hidden constant
+//! `TRY_AS_EXPANDED_{DERIVE}` holding the generated code, to review it without `cargo expand`.
+//!
 //! See also:
 //! * crate [`macros`] (re-export of [`try_as_macros`])
 //! * crate [`traits`] (re-export of [`try_as_traits`])
@@ -99,6 +189,37 @@
 //! assert!(str_ref.is_none());
 //! ```
 //!
+//! The extension traits [`traits::TryAsRefExt`] and [`traits::TryAsMutExt`] provide
+//! shortcuts for common patterns built on top of these:
+//!
+//! ```rust
+//! # mod try_as {
+//! #   pub extern crate try_as_macros as macros;
+//! #   pub extern crate try_as_traits as traits;
+//! # }
+//! use try_as::{
+//!     traits::{TryAsMut, TryAsRefExt, TryAsMutExt},
+//!     macros,
+//! };
+//!
+//! #[derive(macros::From, macros::TryAsRef, macros::TryAsMut)]
+//! enum Value{
+//!     Number(i64),
+//!     String(String),
+//!     Bool(bool)
+//! }
+//!
+//! let mut x = Value::Bool(true);
+//! assert_eq!(x.copied_or_default::<i64>(), 0);
+//! assert_eq!(*x.try_as_ref_or(&7i64), 7);
+//!
+//! *x.get_or_default::<i64>() += 1;
+//! assert_eq!(x.copied_or_default::<i64>(), 1);
+//!
+//! let sum = x.zip_same(&Value::Number(2), |a: &i64, b| a + b);
+//! assert_eq!(sum, Some(3));
+//! ```
+//!
 //! Finally, to inspect the type, we can use the trait `traits::TypedContainer`, which allows
 //! us to look at the [`std::any::TypeId`] of the contained type:
 //! ```
@@ -122,9 +243,16 @@
 //! let boolean: Value = Value::Bool(false);
 //! assert!(x.holds::<i64>());
 //! assert!(!boolean.holds::<i64>());
+//! assert!(x.holds_one_of::<(i64, bool)>());
 //! assert!(std::any::TypeId::of::<bool>() == boolean.type_id());
 //!
+//! use try_as::traits::TypedIteratorExt;
+//! let groups = vec![x, boolean, Value::Number(1)].into_iter().group_by_type();
+//! assert_eq!(groups[&std::any::TypeId::of::<i64>()].len(), 2);
+//!
 //! ```
 
 pub extern crate try_as_macros as macros;
 pub extern crate try_as_traits as traits;
+
+pub use traits::functions::{get, get_mut, holds, take};